@@ -1,7 +1,7 @@
 extern crate toca;
 
 use device_query::Keycode;
-use toca::{KeyboardAction, KeyboardEv, KeyboardPlayer, set_timeout};
+use toca::{KeyboardAction, KeyboardEv, KeyboardPlayer, TypingProfile, set_timeout};
 
 fn from_string() {
     // mock action
@@ -127,7 +127,33 @@ fn from_struct() {
     // auto print: hello world!
 }
 
+fn from_text() {
+    // mock action
+    let mock_action = KeyboardAction::from_text("hello world!", TypingProfile::random((60, 110), (80, 200), 7)).unwrap();
+
+    // simulate
+    let mut player = KeyboardPlayer::new();
+    match player.load(mock_action) {
+        Ok(_) => {
+            set_timeout(|| {
+                match player.do_play() {
+                    Ok(_) => {
+                        println!("done.")
+                    }
+                    Err(_) => {
+                        println!("failed.")
+                    }
+                }
+            }, 3_000)
+        }
+        Err(_) => println!("error when load actions.")
+    }
+
+    // auto print: hello world!
+}
+
 fn main() {
     // from_string();
+    // from_text();
     from_struct();
 }
\ No newline at end of file
@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use device_query::Keycode;
+use crate::{CombinedAction, CombinedEv, Error, KeyboardAction, KeyboardEv, KeyboardMapper, Metadata, MouseAction, MouseEv, MouseMapper, SkippedEvent};
+
+// region layout
+/// first bytes of every binary record
+pub const BINARY_MAGIC: &[u8; 4] = b"TOCA";
+/// version of the binary layout, the byte after `BINARY_MAGIC`
+pub const BINARY_VERSION: u8 = 1;
+
+// kind byte after the version
+const KIND_KEYBOARD: u8 = 1;
+const KIND_MOUSE: u8 = 2;
+const KIND_COMBINED: u8 = 3;
+
+// tag byte of each record
+/// `till`: varint
+const TAG_END: u8 = 0;
+/// new entry of the key table: varint index, varint length, `code` in frontend
+const TAG_KEY: u8 = 1;
+/// key press: zigzag varint delta of timestamp, varint index in the key table
+const TAG_KEY_PRESS: u8 = 2;
+/// key release: same as `TAG_KEY_PRESS`
+const TAG_KEY_RELEASE: u8 = 3;
+/// mouse event: zigzag varint delta of timestamp, `ev_name` byte, zigzag varint x and y
+const TAG_MOUSE: u8 = 4;
+
+fn kind_to_byte(kind: &str) -> Result<u8, Error> {
+    match kind {
+        "keyboard" => Ok(KIND_KEYBOARD),
+        "mouse" => Ok(KIND_MOUSE),
+        "combined" => Ok(KIND_COMBINED),
+        _ => Err(binary_error(format!("unknown kind `{}`", kind)))
+    }
+}
+
+fn binary_error(message: String) -> Error {
+    Error::Parse {
+        line: 0,
+        column: 0,
+        message,
+    }
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut n: u64) -> Result<(), Error> {
+    let mut buffer = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buffer[len] = byte;
+            len += 1;
+            break;
+        }
+        buffer[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buffer[..len])?;
+    Ok(())
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, Error> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, Error> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(binary_error(String::from("varint is too long")))
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = read_varint(reader)? as usize;
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    match bytes.len() == len {
+        true => Ok(bytes),
+        false => Err(Error::Io(ErrorKind::UnexpectedEof.into()))
+    }
+}
+// endregion
+
+// region writer
+/// Write a record event by event, e.g. while it is being recorded.
+/// Nothing is buffered, wrap `writer` in a `BufWriter` for files.
+pub struct BinaryWriter<W: Write> {
+    writer: W,
+    /// index of each key in the key table
+    keys: HashMap<Keycode, u64>,
+    last: u64,
+}
+
+impl<W: Write> BinaryWriter<W> {
+    /// write the header, `kind` is `keyboard`, `mouse` or `combined`
+    pub fn new(mut writer: W, kind: &str, meta: &Metadata) -> Result<BinaryWriter<W>, Error> {
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&[BINARY_VERSION, kind_to_byte(kind)?])?;
+        let meta = serde_json::to_vec(meta)?;
+        write_varint(&mut writer, meta.len() as u64)?;
+        writer.write_all(&meta)?;
+
+        Ok(BinaryWriter {
+            writer,
+            keys: HashMap::new(),
+            last: 0,
+        })
+    }
+
+    fn write_delta(&mut self, timestamp: u64) -> Result<(), Error> {
+        let delta = match i64::try_from(timestamp) {
+            // `last` passed the same check
+            Ok(timestamp) => timestamp - self.last as i64,
+            Err(_) => return Err(binary_error(format!("timestamp {} is out of range, the max is {}", timestamp, i64::MAX)))
+        };
+        self.last = timestamp;
+        write_varint(&mut self.writer, zigzag(delta))
+    }
+
+    /// It fails with `Error::UnknownKey` for a key without `code` in frontend.
+    pub fn write_keyboard_ev(&mut self, ev: &KeyboardEv) -> Result<(), Error> {
+        let index = match self.keys.get(&ev.code) {
+            Some(index) => *index,
+            None => {
+                let code = KeyboardMapper::dq_to_front_checked(ev.code)?;
+                let index = self.keys.len() as u64;
+                self.keys.insert(ev.code, index);
+                self.writer.write_all(&[TAG_KEY])?;
+                write_varint(&mut self.writer, index)?;
+                write_varint(&mut self.writer, code.len() as u64)?;
+                self.writer.write_all(code.as_bytes())?;
+                index
+            }
+        };
+
+        self.writer.write_all(&[if ev.press { TAG_KEY_PRESS } else { TAG_KEY_RELEASE }])?;
+        self.write_delta(ev.timestamp)?;
+        write_varint(&mut self.writer, index)
+    }
+
+    pub fn write_mouse_ev(&mut self, ev: &MouseEv) -> Result<(), Error> {
+        self.writer.write_all(&[TAG_MOUSE])?;
+        self.write_delta(ev.timestamp)?;
+        self.writer.write_all(&[MouseMapper::ev_name_to_number(ev.ev_name) as u8])?;
+        write_varint(&mut self.writer, zigzag(ev.position.0 as i64))?;
+        write_varint(&mut self.writer, zigzag(ev.position.1 as i64))
+    }
+
+    /// write the end record and give the writer back
+    pub fn finish(mut self, till: u64) -> Result<W, Error> {
+        self.writer.write_all(&[TAG_END])?;
+        write_varint(&mut self.writer, till)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+// endregion
+
+// region reader
+/// a record read by `BinaryReader`
+pub enum BinaryRecord {
+    Keyboard(KeyboardEv),
+    Mouse(MouseEv),
+    /// `till` of the action, the last record
+    End(u64),
+}
+
+/// Read a record event by event.
+/// Events with unknown codes are left out, as `from_string` does.
+pub struct BinaryReader<R: Read> {
+    reader: R,
+    kind: &'static str,
+    meta: Metadata,
+    keys: Vec<Option<Keycode>>,
+    last: u64,
+    done: bool,
+}
+
+impl<R: Read> BinaryReader<R> {
+    /// read the header
+    pub fn new(mut reader: R) -> Result<BinaryReader<R>, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != BINARY_MAGIC {
+            return Err(binary_error(String::from("not a binary record of toca")));
+        }
+        let version = read_u8(&mut reader)?;
+        if version > BINARY_VERSION {
+            return Err(binary_error(format!("unsupported binary version {}, the latest is {}", version, BINARY_VERSION)));
+        }
+        let kind = match read_u8(&mut reader)? {
+            KIND_KEYBOARD => "keyboard",
+            KIND_MOUSE => "mouse",
+            KIND_COMBINED => "combined",
+            kind => return Err(binary_error(format!("unknown kind {}", kind)))
+        };
+        let meta = serde_json::from_slice(&read_bytes(&mut reader)?)?;
+
+        Ok(BinaryReader {
+            reader,
+            kind,
+            meta,
+            keys: vec![],
+            last: 0,
+            done: false,
+        })
+    }
+
+    /// `keyboard`, `mouse` or `combined`
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.meta
+    }
+
+    fn read_timestamp(&mut self) -> Result<u64, Error> {
+        let delta = unzigzag(read_varint(&mut self.reader)?);
+        let timestamp = match delta >= 0 {
+            true => self.last.checked_add(delta as u64),
+            false => self.last.checked_sub(delta.unsigned_abs())
+        };
+        self.last = match timestamp {
+            Some(timestamp) => timestamp,
+            None => return Err(binary_error(format!("timestamp {} {:+} is out of range", self.last, delta)))
+        };
+        Ok(self.last)
+    }
+
+    /// the next record, `None` after `BinaryRecord::End`
+    pub fn next_record(&mut self) -> Result<Option<BinaryRecord>, Error> {
+        while !self.done {
+            match read_u8(&mut self.reader)? {
+                TAG_END => {
+                    self.done = true;
+                    return Ok(Some(BinaryRecord::End(read_varint(&mut self.reader)?)));
+                }
+                TAG_KEY => {
+                    let index = read_varint(&mut self.reader)? as usize;
+                    let code = String::from_utf8_lossy(&read_bytes(&mut self.reader)?).to_string();
+                    if index != self.keys.len() {
+                        return Err(binary_error(format!("key #{} is defined out of order", index)));
+                    }
+                    self.keys.push(KeyboardMapper::front_to_dq(&code));
+                }
+                tag @ (TAG_KEY_PRESS | TAG_KEY_RELEASE) => {
+                    let timestamp = self.read_timestamp()?;
+                    let index = read_varint(&mut self.reader)? as usize;
+                    match self.keys.get(index) {
+                        Some(Some(code)) => return Ok(Some(BinaryRecord::Keyboard(KeyboardEv {
+                            code: *code,
+                            press: tag == TAG_KEY_PRESS,
+                            timestamp,
+                        }))),
+                        Some(None) => (),
+                        None => return Err(binary_error(format!("key #{} is not defined", index)))
+                    }
+                }
+                TAG_MOUSE => {
+                    let timestamp = self.read_timestamp()?;
+                    let ev_name = read_u8(&mut self.reader)?;
+                    let x = unzigzag(read_varint(&mut self.reader)?) as i32;
+                    let y = unzigzag(read_varint(&mut self.reader)?) as i32;
+                    if let Ok(ev_name) = MouseMapper::number_to_ev_name(ev_name as usize) {
+                        return Ok(Some(BinaryRecord::Mouse(MouseEv {
+                            ev_name,
+                            position: (x, y),
+                            timestamp,
+                        })));
+                    }
+                }
+                tag => return Err(binary_error(format!("unknown tag {}", tag)))
+            }
+        }
+        Ok(None)
+    }
+
+    fn expect_kind(&self, kind: &str) -> Result<(), Error> {
+        match self.kind == kind {
+            true => Ok(()),
+            false => Err(binary_error(format!("expect a {} record, found a {} record", kind, self.kind)))
+        }
+    }
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    pub fn write_binary<W: Write>(&self, writer: W, meta: &Metadata) -> Result<(), Error> {
+        let mut writer = BinaryWriter::new(writer, "keyboard", meta)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            writer.write_keyboard_ev(ev).map_err(|reason| SkippedEvent { index, reason }.into_error())?;
+        }
+        writer.finish(self.till)?;
+        Ok(())
+    }
+
+    pub fn read_binary<R: Read>(reader: R) -> Result<(KeyboardAction, Metadata), Error> {
+        let mut reader = BinaryReader::new(reader)?;
+        reader.expect_kind("keyboard")?;
+        let mut action = KeyboardAction { evs: vec![], till: 0 };
+
+        loop {
+            match reader.next_record()? {
+                Some(BinaryRecord::Keyboard(ev)) => action.evs.push(ev),
+                Some(BinaryRecord::Mouse(_)) => (),
+                Some(BinaryRecord::End(till)) => action.till = till,
+                None => break
+            }
+        }
+
+        Ok((action, reader.meta))
+    }
+}
+
+impl MouseAction {
+    pub fn write_binary<W: Write>(&self, writer: W, meta: &Metadata) -> Result<(), Error> {
+        let mut writer = BinaryWriter::new(writer, "mouse", meta)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            writer.write_mouse_ev(ev).map_err(|reason| SkippedEvent { index, reason }.into_error())?;
+        }
+        writer.finish(self.till)?;
+        Ok(())
+    }
+
+    pub fn read_binary<R: Read>(reader: R) -> Result<(MouseAction, Metadata), Error> {
+        let mut reader = BinaryReader::new(reader)?;
+        reader.expect_kind("mouse")?;
+        let mut action = MouseAction { evs: vec![], till: 0 };
+
+        loop {
+            match reader.next_record()? {
+                Some(BinaryRecord::Mouse(ev)) => action.evs.push(ev),
+                Some(BinaryRecord::Keyboard(_)) => (),
+                Some(BinaryRecord::End(till)) => action.till = till,
+                None => break
+            }
+        }
+
+        Ok((action, reader.meta))
+    }
+}
+
+impl CombinedAction {
+    pub fn write_binary<W: Write>(&self, writer: W, meta: &Metadata) -> Result<(), Error> {
+        let mut writer = BinaryWriter::new(writer, "combined", meta)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            match ev {
+                CombinedEv::Keyboard(ev) => writer.write_keyboard_ev(ev),
+                CombinedEv::Mouse(ev) => writer.write_mouse_ev(ev),
+            }.map_err(|reason| SkippedEvent { index, reason }.into_error())?;
+        }
+        writer.finish(self.till)?;
+        Ok(())
+    }
+
+    pub fn read_binary<R: Read>(reader: R) -> Result<(CombinedAction, Metadata), Error> {
+        let mut reader = BinaryReader::new(reader)?;
+        reader.expect_kind("combined")?;
+        let mut action = CombinedAction { evs: vec![], till: 0 };
+
+        loop {
+            match reader.next_record()? {
+                Some(BinaryRecord::Keyboard(ev)) => action.evs.push(CombinedEv::Keyboard(ev)),
+                Some(BinaryRecord::Mouse(ev)) => action.evs.push(CombinedEv::Mouse(ev)),
+                Some(BinaryRecord::End(till)) => action.till = till,
+                None => break
+            }
+        }
+
+        Ok((action, reader.meta))
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::to_value;
+    use crate::{KeyMap, MouseEventName};
+
+    #[test]
+    fn varint() {
+        for n in [0, 1, 127, 128, 300, u64::MAX] {
+            let mut buffer = vec![];
+            write_varint(&mut buffer, n).unwrap();
+            assert_eq!(read_varint(&mut buffer.as_slice()).unwrap(), n);
+        }
+        for n in [0, -1, 1, i64::MIN, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(n)), n);
+        }
+    }
+
+    /// the binary form carries the same action as the json form
+    #[test]
+    fn keyboard_round_trip() {
+        let mut evs = vec![];
+        for i in 0..100u64 {
+            let code = if i % 3 == 0 { Keycode::LShift } else { Keycode::A };
+            evs.push(KeyboardEv { code, press: true, timestamp: i * 200 });
+            evs.push(KeyboardEv { code, press: false, timestamp: i * 200 + 80 });
+        }
+        let action = KeyboardAction { evs, till: 20000 };
+
+        let mut buffer = vec![];
+        action.write_binary(&mut buffer, &Metadata::new()).unwrap();
+        assert!(buffer.starts_with(b"TOCA\x01\x01"));
+        assert!(buffer.len() < action.to_string().unwrap().len() / 4);
+
+        let (again, meta) = KeyboardAction::read_binary(buffer.as_slice()).unwrap();
+        assert_eq!(to_value(again.to_serializable(&KeyMap::new())).unwrap(), to_value(action.to_serializable(&KeyMap::new())).unwrap());
+        assert_eq!(meta.host_os, std::env::consts::OS);
+    }
+
+    #[test]
+    fn mouse_round_trip() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (-1920, 1080), timestamp: 100 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (0, 0), timestamp: 50 },
+            ],
+            till: 300,
+        };
+
+        let mut buffer = vec![];
+        action.write_binary(&mut buffer, &Metadata::default()).unwrap();
+        let (again, _) = MouseAction::read_binary(buffer.as_slice()).unwrap();
+        assert_eq!(to_value(again.to_serializable()).unwrap(), to_value(action.to_serializable()).unwrap());
+    }
+
+    #[test]
+    fn read_bad_binary() {
+        assert!(matches!(MouseAction::read_binary(&b"{\"evs\":[]}"[..]), Err(Error::Parse { .. })));
+
+        let mut buffer = vec![];
+        KeyboardAction { evs: vec![], till: 0 }.write_binary(&mut buffer, &Metadata::default()).unwrap();
+        assert!(matches!(MouseAction::read_binary(buffer.as_slice()), Err(Error::Parse { .. })));
+
+        // truncated before the end record
+        buffer.truncate(buffer.len() - 2);
+        assert!(matches!(KeyboardAction::read_binary(buffer.as_slice()), Err(Error::Io(_))));
+
+        // a mouse event before timestamp 0
+        let mut buffer = vec![];
+        MouseAction { evs: vec![], till: 0 }.write_binary(&mut buffer, &Metadata::default()).unwrap();
+        buffer.truncate(buffer.len() - 2);
+        buffer.extend([TAG_MOUSE]);
+        write_varint(&mut buffer, zigzag(-1)).unwrap();
+        buffer.extend([1, 0, 0, TAG_END, 0]);
+        assert!(matches!(MouseAction::read_binary(buffer.as_slice()), Err(Error::Parse { .. })));
+    }
+
+    /// a timestamp the delta can not hold fails with the index of the event
+    #[test]
+    fn write_bad_timestamp() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (1, 2), timestamp: 10 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (1, 2), timestamp: u64::MAX },
+            ],
+            till: u64::MAX,
+        };
+        match action.write_binary(vec![], &Metadata::default()) {
+            Err(Error::Event { index, cause }) => {
+                assert_eq!(index, 1);
+                assert!(matches!(*cause, Error::Parse { .. }));
+            }
+            _ => panic!("expect an invalid event"),
+        }
+    }
+}
+// endregion
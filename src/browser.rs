@@ -0,0 +1,198 @@
+use crate::{KeyboardAction, KeyboardMapper, MouseAction, MouseEventName, MouseMapper};
+
+// region browser script
+/// the browser automation library a script is written for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrowserDriver {
+    /// waits with `page.waitForTimeout`
+    Playwright,
+    /// waits with a `setTimeout` promise, `waitForTimeout` is removed from recent versions
+    Puppeteer,
+}
+
+enum Step {
+    Key { code: &'static str, press: bool },
+    Mouse { button: &'static str, press: bool, position: (i32, i32) },
+    Click { button: &'static str, position: (i32, i32), delay: u64 },
+}
+
+/// `button` of the mouse methods
+fn button_name(ev_name: MouseEventName) -> &'static str {
+    let (button, _) = MouseMapper::ev_name_to_dq(ev_name);
+    match MouseMapper::dq_to_front(button) {
+        Some(1) => "middle",
+        Some(2) => "right",
+        _ => "left"
+    }
+}
+
+/// `KeyboardEvent.code` the browser takes,
+/// `BackSlash` of `KeyboardMapper::dq_to_front` is `Backslash` in the standard.
+fn key_name(code: &'static str) -> &'static str {
+    match code {
+        "BackSlash" => "Backslash",
+        _ => code
+    }
+}
+
+fn wait(driver: BrowserDriver, ms: u64) -> String {
+    match driver {
+        BrowserDriver::Playwright => format!("await page.waitForTimeout({});\n", ms),
+        BrowserDriver::Puppeteer => format!("await new Promise(resolve => setTimeout(resolve, {}));\n", ms),
+    }
+}
+
+/// Render the actions as the body of an async function with a `page` in scope.
+/// A press and a release of the same button at the same position without other events between them are merged into
+/// `page.mouse.click`.
+pub(crate) fn render_browser_script(driver: BrowserDriver, keyboard: Option<&KeyboardAction>, mouse: Option<&MouseAction>) -> String {
+    let mut steps: Vec<(u64, Step)> = vec![];
+    let mut till = 0;
+
+    if let Some(keyboard) = keyboard {
+        for ev in keyboard.evs.iter() {
+            if let Some(code) = KeyboardMapper::dq_to_front(ev.code) {
+                steps.push((ev.timestamp, Step::Key { code: key_name(code), press: ev.press }));
+            }
+        }
+        till = till.max(keyboard.till);
+    }
+    if let Some(mouse) = mouse {
+        for ev in mouse.evs.iter() {
+            let (_, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+            steps.push((ev.timestamp, Step::Mouse { button: button_name(ev.ev_name), press, position: ev.position }));
+        }
+        till = till.max(mouse.till);
+    }
+    // stable, the keyboard goes first at the same time
+    steps.sort_by_key(|(timestamp, _)| *timestamp);
+
+    // merge the clicks
+    let mut merged: Vec<(u64, Step)> = vec![];
+    for (timestamp, step) in steps {
+        if let Step::Mouse { button, press: false, position } = step {
+            if let Some((down_at, Step::Mouse { button: down_button, press: true, position: down_position })) = merged.last() {
+                if *down_button == button && *down_position == position {
+                    let (down_at, delay) = (*down_at, timestamp - *down_at);
+                    merged.pop();
+                    merged.push((down_at, Step::Click { button, position, delay }));
+                    continue;
+                }
+            }
+        }
+        merged.push((timestamp, step));
+    }
+
+    let mut script = String::from("// generated by toca\n");
+    let mut now = 0;
+    let mut cursor = None;
+    for (timestamp, step) in merged {
+        if timestamp > now {
+            script.push_str(&wait(driver, timestamp - now));
+            now = timestamp;
+        }
+        match step {
+            Step::Key { code, press } => {
+                let method = if press { "down" } else { "up" };
+                script.push_str(&format!("await page.keyboard.{}('{}');\n", method, code));
+            }
+            Step::Mouse { button, press, position } => {
+                if cursor != Some(position) {
+                    script.push_str(&format!("await page.mouse.move({}, {});\n", position.0, position.1));
+                    cursor = Some(position);
+                }
+                let method = if press { "down" } else { "up" };
+                script.push_str(&format!("await page.mouse.{}({{ button: '{}' }});\n", method, button));
+            }
+            Step::Click { button, position, delay } => {
+                script.push_str(&format!(
+                    "await page.mouse.click({}, {}, {{ button: '{}', delay: {} }});\n",
+                    position.0, position.1, button, delay
+                ));
+                cursor = Some(position);
+                // the click waits for the delay itself
+                now += delay;
+            }
+        }
+    }
+    if till > now {
+        script.push_str(&wait(driver, till - now));
+    }
+    script
+}
+
+impl KeyboardAction {
+    /// a snippet of Playwright or Puppeteer which plays the action in a `page`, e.g. to seed an e2e test
+    pub fn to_browser_script(&self, driver: BrowserDriver) -> String {
+        render_browser_script(driver, Some(self), None)
+    }
+}
+
+impl MouseAction {
+    /// a snippet of Playwright or Puppeteer which plays the action in a `page`, e.g. to seed an e2e test.
+    /// positions are taken as they are, the screen and the viewport may differ.
+    pub fn to_browser_script(&self, driver: BrowserDriver) -> String {
+        render_browser_script(driver, None, Some(self))
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use device_query::Keycode;
+    use crate::{KeyboardEv, MouseEv};
+
+    #[test]
+    fn keyboard_script() {
+        let action = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::LShift, press: true, timestamp: 0 },
+                KeyboardEv { code: Keycode::BackSlash, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::BackSlash, press: false, timestamp: 180 },
+                KeyboardEv { code: Keycode::LShift, press: false, timestamp: 180 },
+            ],
+            till: 200,
+        };
+        assert_eq!(
+            action.to_browser_script(BrowserDriver::Playwright),
+            "// generated by toca\n\
+            await page.keyboard.down('ShiftLeft');\n\
+            await page.waitForTimeout(100);\n\
+            await page.keyboard.down('Backslash');\n\
+            await page.waitForTimeout(80);\n\
+            await page.keyboard.up('Backslash');\n\
+            await page.keyboard.up('ShiftLeft');\n\
+            await page.waitForTimeout(20);\n"
+        );
+        assert!(action.to_browser_script(BrowserDriver::Puppeteer).contains("await new Promise(resolve => setTimeout(resolve, 80));\n"));
+    }
+
+    /// a press and a release at the same position become a click, a drag stays as it is
+    #[test]
+    fn mouse_script() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (10, 20), timestamp: 100 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (10, 20), timestamp: 150 },
+                MouseEv { ev_name: MouseEventName::RightDown, position: (30, 40), timestamp: 500 },
+                MouseEv { ev_name: MouseEventName::RightUp, position: (50, 60), timestamp: 600 },
+            ],
+            till: 600,
+        };
+        assert_eq!(
+            action.to_browser_script(BrowserDriver::Playwright),
+            "// generated by toca\n\
+            await page.waitForTimeout(100);\n\
+            await page.mouse.click(10, 20, { button: 'left', delay: 50 });\n\
+            await page.waitForTimeout(350);\n\
+            await page.mouse.move(30, 40);\n\
+            await page.mouse.down({ button: 'right' });\n\
+            await page.waitForTimeout(100);\n\
+            await page.mouse.move(50, 60);\n\
+            await page.mouse.up({ button: 'right' });\n"
+        );
+    }
+}
+// endregion
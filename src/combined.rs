@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use serde_json::to_string as json_stringify;
+use crate::{BrowserDriver, Document, Error, KeyboardAction, KeyboardActionSerializable, KeyboardEv, KeyMap, Metadata, MouseAction, MouseActionSerializable, MouseEv, parse_document, parse_dsl, parse_xdotool};
+use crate::browser::render_browser_script;
+use crate::dsl::render_dsl;
+use crate::xdotool::render_xdotool;
+
+// region combined action
+/// a keyboard or mouse event on a shared timeline
+#[derive(Copy, Clone)]
+pub enum CombinedEv {
+    Keyboard(KeyboardEv),
+    Mouse(MouseEv),
+}
+
+impl CombinedEv {
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            CombinedEv::Keyboard(ev) => ev.timestamp,
+            CombinedEv::Mouse(ev) => ev.timestamp,
+        }
+    }
+}
+
+/// keyboard and mouse events in one ordered timeline, see `CombinedAction::merge`
+pub struct CombinedAction {
+    pub evs: Vec<CombinedEv>,
+    pub till: u64,
+}
+
+/// the serialized form keeps the two halves apart, they are merged again when parsed
+#[derive(Deserialize, Serialize)]
+pub struct CombinedActionSerializable {
+    pub keyboard: KeyboardActionSerializable,
+    pub mouse: MouseActionSerializable,
+    pub till: u64,
+}
+
+impl CombinedAction {
+    /// Merge the actions into one timeline, the mouse events `mouse_offset` ms after the keyboard ones
+    /// (before if negative; the timeline starts at 0 either way).
+    /// Events at the same time are ordered keyboard first, then as they are in their action.
+    pub fn merge(keyboard: &KeyboardAction, mouse: &MouseAction, mouse_offset: i64) -> CombinedAction {
+        let (keyboard_shift, mouse_shift) = match mouse_offset >= 0 {
+            true => (0, mouse_offset as u64),
+            false => (mouse_offset.unsigned_abs(), 0)
+        };
+
+        // (timestamp, keyboard first, index) is unique, so the order does not depend on the sort
+        let mut keyed: Vec<(u64, u8, usize, CombinedEv)> = vec![];
+        for (index, ev) in keyboard.evs.iter().enumerate() {
+            let ev = KeyboardEv { timestamp: ev.timestamp + keyboard_shift, ..*ev };
+            keyed.push((ev.timestamp, 0, index, CombinedEv::Keyboard(ev)));
+        }
+        for (index, ev) in mouse.evs.iter().enumerate() {
+            let ev = MouseEv { timestamp: ev.timestamp + mouse_shift, ..*ev };
+            keyed.push((ev.timestamp, 1, index, CombinedEv::Mouse(ev)));
+        }
+        keyed.sort_by_key(|(timestamp, source, index, _)| (*timestamp, *source, *index));
+
+        CombinedAction {
+            evs: keyed.into_iter().map(|(_, _, _, ev)| ev).collect(),
+            till: (keyboard.till + keyboard_shift).max(mouse.till + mouse_shift),
+        }
+    }
+
+    /// `merge` with the offset from the start times of the records (`Metadata.created_at`, stamped by the recorders
+    /// when their timestamps start, see `get_metadata`), for a keyboard and a mouse record of the same session.
+    /// Without both start times the offset is 0.
+    pub fn merge_by_start(keyboard: &KeyboardAction, keyboard_meta: &Metadata, mouse: &MouseAction, mouse_meta: &Metadata) -> CombinedAction {
+        let mouse_offset = match (keyboard_meta.created_at, mouse_meta.created_at) {
+            (0, _) | (_, 0) => 0,
+            (keyboard_start, mouse_start) => mouse_start as i64 - keyboard_start as i64
+        };
+        CombinedAction::merge(keyboard, mouse, mouse_offset)
+    }
+
+    /// Split the timeline back into a keyboard and a mouse action, both end at `till`.
+    pub fn split(&self) -> (KeyboardAction, MouseAction) {
+        let mut keyboard = KeyboardAction { evs: vec![], till: self.till };
+        let mut mouse = MouseAction { evs: vec![], till: self.till };
+        for ev in self.evs.iter() {
+            match ev {
+                CombinedEv::Keyboard(ev) => keyboard.evs.push(*ev),
+                CombinedEv::Mouse(ev) => mouse.evs.push(*ev),
+            }
+        }
+        (keyboard, mouse)
+    }
+
+    pub fn from_string(string_source: &str) -> Result<CombinedAction, Error> {
+        Ok(CombinedAction::from_string_with_meta(string_source)?.0)
+    }
+
+    /// `from_string` but the metadata in the header is returned beside the action.
+    pub fn from_string_with_meta(string_source: &str) -> Result<(CombinedAction, Metadata), Error> {
+        let document: Document<CombinedActionSerializable> = parse_document(string_source, "combined")?;
+        Ok((CombinedAction::from_serializable(&document.action), document.meta))
+    }
+
+    /// Events with unknown codes are left out, as `from_string` of the halves does.
+    pub fn from_serializable(source: &CombinedActionSerializable) -> CombinedAction {
+        let (keyboard, _) = KeyboardAction::from_serializable(&source.keyboard, &KeyMap::new());
+        let (mouse, _) = MouseAction::from_serializable(&source.mouse);
+        let mut action = CombinedAction::merge(&keyboard, &mouse, 0);
+        action.till = action.till.max(source.till);
+        action
+    }
+
+    pub fn to_string(&self) -> Result<String, Error> {
+        self.to_string_with_meta(&Metadata::of_host())
+    }
+
+    /// `to_string` with the given metadata in the header.
+    pub fn to_string_with_meta(&self, meta: &Metadata) -> Result<String, Error> {
+        let document = Document::new("combined", meta.clone(), self.to_serializable_strict()?);
+        match json_stringify(&document) {
+            Ok(s) => Ok(s),
+            Err(err) => Err(Error::from(err))
+        }
+    }
+
+    /// Keys without `code` in frontend are left out, see `KeyboardAction.to_serializable`.
+    pub fn to_serializable(&self) -> CombinedActionSerializable {
+        let (keyboard, mouse) = self.split();
+        CombinedActionSerializable {
+            keyboard: keyboard.to_serializable(&KeyMap::new()),
+            mouse: mouse.to_serializable(),
+            till: self.till,
+        }
+    }
+
+    /// `to_serializable` of the writers, it fails on the first key without `code` in frontend with the index of the
+    /// event in the timeline.
+    pub(crate) fn to_serializable_strict(&self) -> Result<CombinedActionSerializable, Error> {
+        let (keyboard, mouse) = self.split();
+        let keyboard = keyboard.to_serializable_strict(&KeyMap::new()).map_err(|err| match err {
+            // the index in the keyboard half => the index in the timeline
+            Error::Event { index, cause } => Error::Event {
+                index: self.evs.iter().enumerate()
+                    .filter(|(_, ev)| matches!(ev, CombinedEv::Keyboard(_)))
+                    .nth(index)
+                    .map_or(index, |(index, _)| index),
+                cause,
+            },
+            err => err
+        })?;
+        Ok(CombinedActionSerializable {
+            keyboard,
+            mouse: mouse.to_serializable(),
+            till: self.till,
+        })
+    }
+
+    /// see `KeyboardAction.to_xdotool`
+    pub fn to_xdotool(&self) -> String {
+        let (keyboard, mouse) = self.split();
+        render_xdotool(Some(&keyboard), Some(&mouse))
+    }
+
+    /// see `parse_xdotool`
+    pub fn from_xdotool(script: &str) -> Result<CombinedAction, Error> {
+        let (keyboard, mouse) = parse_xdotool(script)?;
+        Ok(CombinedAction::merge(&keyboard, &mouse, 0))
+    }
+
+    /// see `KeyboardAction.to_browser_script`
+    pub fn to_browser_script(&self, driver: BrowserDriver) -> String {
+        let (keyboard, mouse) = self.split();
+        render_browser_script(driver, Some(&keyboard), Some(&mouse))
+    }
+
+    /// see `parse_dsl`
+    pub fn to_dsl(&self) -> String {
+        let (keyboard, mouse) = self.split();
+        render_dsl(Some(&keyboard), Some(&mouse))
+    }
+
+    /// see `parse_dsl`
+    pub fn from_dsl(script: &str) -> Result<CombinedAction, Error> {
+        let (keyboard, mouse) = parse_dsl(script)?;
+        Ok(CombinedAction::merge(&keyboard, &mouse, 0))
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use device_query::Keycode;
+    use crate::{Format, MouseEventName};
+
+    fn keyboard() -> KeyboardAction {
+        KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 200 },
+            ],
+            till: 300,
+        }
+    }
+
+    fn mouse() -> MouseAction {
+        MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (1, 2), timestamp: 0 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (1, 2), timestamp: 100 },
+            ],
+            till: 150,
+        }
+    }
+
+    /// (is keyboard, timestamp) of each event
+    fn timeline(action: &CombinedAction) -> Vec<(bool, u64)> {
+        action.evs.iter().map(|ev| (matches!(ev, CombinedEv::Keyboard(_)), ev.timestamp())).collect()
+    }
+
+    #[test]
+    fn merge_and_split() {
+        let combined = CombinedAction::merge(&keyboard(), &mouse(), 100);
+        // the keyboard goes first at 200
+        assert_eq!(timeline(&combined), vec![(true, 100), (false, 100), (true, 200), (false, 200)]);
+        assert_eq!(combined.till, 300);
+
+        let combined = CombinedAction::merge(&keyboard(), &mouse(), -100);
+        assert_eq!(timeline(&combined), vec![(false, 0), (false, 100), (true, 200), (true, 300)]);
+        assert_eq!(combined.till, 400);
+
+        let (keyboard, mouse) = combined.split();
+        assert_eq!(keyboard.evs[0].timestamp, 200);
+        assert_eq!(mouse.evs.len(), 2);
+        assert_eq!(mouse.till, 400);
+    }
+
+    #[test]
+    fn merge_by_start() {
+        let keyboard_meta = Metadata { created_at: 10_000, ..Metadata::default() };
+        let mouse_meta = Metadata { created_at: 10_050, ..Metadata::default() };
+        let combined = CombinedAction::merge_by_start(&keyboard(), &keyboard_meta, &mouse(), &mouse_meta);
+        assert_eq!(timeline(&combined), vec![(false, 50), (true, 100), (false, 150), (true, 200)]);
+    }
+
+    /// every format keeps the order of the timeline
+    #[test]
+    fn combined_formats() {
+        let combined = CombinedAction::merge(&keyboard(), &mouse(), 100);
+        let s = combined.to_string().unwrap();
+        assert!(s.contains("\"kind\":\"combined\""));
+        assert!(KeyboardAction::from_string(&s).is_err());
+
+        for format in [Format::Json, Format::PrettyJson, Format::Binary, Format::JsonLines, Format::Csv] {
+            let mut buffer = vec![];
+            combined.write_to(&mut buffer, format).unwrap();
+            let again = CombinedAction::read_from(buffer.as_slice(), format).unwrap();
+            assert_eq!(timeline(&again), timeline(&combined), "{:?}", format);
+            assert_eq!(again.till, 300);
+        }
+    }
+}
+// endregion
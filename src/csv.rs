@@ -0,0 +1,329 @@
+use std::io::{BufRead, Write};
+use crate::{CombinedAction, CombinedEv, Error, KeyboardAction, KeyboardEv, KeyboardMapper, MouseAction, MouseEv, MouseMapper, SkippedEvent};
+
+// region csv
+/// the header row of the csv form
+pub const CSV_HEADER: &str = "timestamp,type,code,button,press,x,y";
+
+/// Rows of the csv form, one event per row:
+/// - `key`: `code` in frontend (see `KeyboardMapper::dq_to_front`) and `press`
+/// - `mouse`: `button` in frontend (0: left, 1: middle, 2: right), `press` and the position `x`, `y`
+/// - `end`: the `timestamp` is `till` of the action
+///
+/// e.g.
+/// ```text
+/// timestamp,type,code,button,press,x,y
+/// 10,key,KeyA,,true,,
+/// 20,mouse,,0,true,100,200
+/// 300,end,,,,,
+/// ```
+enum CsvRow {
+    Key(KeyboardEv),
+    Mouse(MouseEv),
+    End(u64),
+}
+
+fn row_error(line: usize, column: usize, message: String) -> Error {
+    Error::Parse {
+        line,
+        column,
+        message,
+    }
+}
+
+/// a field without the quotes a spreadsheet may add
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    match field.len() >= 2 && field.starts_with('"') && field.ends_with('"') {
+        true => &field[1..field.len() - 1],
+        false => field
+    }
+}
+
+fn parse_row(line: usize, row: &str) -> Result<CsvRow, Error> {
+    let fields: Vec<&str> = row.split(',').map(unquote).collect();
+    if fields.len() != 7 {
+        return Err(row_error(line, 0, format!("expect 7 fields, found {}", fields.len())));
+    }
+
+    fn parse<T: std::str::FromStr>(line: usize, column: usize, fields: &[&str], name: &str) -> Result<T, Error> {
+        fields[column - 1].parse().map_err(|_| row_error(line, column, format!("invalid {} `{}`", name, fields[column - 1])))
+    }
+    fn parse_press(line: usize, fields: &[&str]) -> Result<bool, Error> {
+        match fields[4] {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            press => Err(row_error(line, 5, format!("invalid press `{}`", press)))
+        }
+    }
+
+    let timestamp: u64 = parse(line, 1, &fields, "timestamp")?;
+    match fields[1] {
+        "key" => match KeyboardMapper::front_to_dq(fields[2]) {
+            Some(code) => Ok(CsvRow::Key(KeyboardEv {
+                code,
+                press: parse_press(line, &fields)?,
+                timestamp,
+            })),
+            None => Err(row_error(line, 3, format!("unknown key `{}`", fields[2])))
+        },
+        "mouse" => {
+            let button: usize = parse(line, 4, &fields, "button")?;
+            let press = parse_press(line, &fields)?;
+            let ev_name = match MouseMapper::front_to_dq(button).and_then(|button| MouseMapper::dq_to_ev_name(button, press)) {
+                Some(ev_name) => ev_name,
+                None => return Err(row_error(line, 4, format!("unknown button `{}`", button)))
+            };
+            Ok(CsvRow::Mouse(MouseEv {
+                ev_name,
+                position: (parse(line, 6, &fields, "x")?, parse(line, 7, &fields, "y")?),
+                timestamp,
+            }))
+        }
+        "end" => Ok(CsvRow::End(timestamp)),
+        kind => Err(row_error(line, 2, format!("unknown type `{}`", kind)))
+    }
+}
+
+/// Read the rows of the csv form, the invalid rows are left out and returned beside with their line and column
+/// (from 1) in `Error::Parse`. It fails only if the header is wrong or the reader fails.
+fn read_rows<R: BufRead>(reader: R) -> Result<(Vec<CsvRow>, Vec<SkippedEvent>), Error> {
+    let mut rows = vec![];
+    let mut skipped = vec![];
+    let mut header = false;
+
+    for (line, row) in reader.lines().enumerate() {
+        let row = row?;
+        if row.trim().is_empty() {
+            continue;
+        }
+        if !header {
+            let fields: Vec<&str> = row.split(',').map(unquote).collect();
+            if fields.join(",") != CSV_HEADER {
+                return Err(row_error(line + 1, 1, format!("expect the header `{}`", CSV_HEADER)));
+            }
+            header = true;
+            continue;
+        }
+        let index = rows.len() + skipped.len();
+        match parse_row(line + 1, &row) {
+            Ok(row) => rows.push(row),
+            Err(reason) => skipped.push(SkippedEvent { index, reason })
+        }
+    }
+
+    Ok((rows, skipped))
+}
+
+/// the action if no row is invalid, or the error of the first invalid row
+fn first_error<T>((action, mut skipped): (T, Vec<SkippedEvent>)) -> Result<T, Error> {
+    match skipped.is_empty() {
+        true => Ok(action),
+        false => Err(skipped.remove(0).reason)
+    }
+}
+
+/// fails with the index of the event and `Error::UnknownKey` for a key without `code` in frontend
+fn write_key_row<W: Write>(writer: &mut W, index: usize, ev: &KeyboardEv) -> Result<(), Error> {
+    let code = KeyboardMapper::dq_to_front_checked(ev.code).map_err(|reason| SkippedEvent { index, reason }.into_error())?;
+    writeln!(writer, "{},key,{},,{},,", ev.timestamp, code, ev.press)?;
+    Ok(())
+}
+
+fn write_mouse_row<W: Write>(writer: &mut W, ev: &MouseEv) -> Result<(), Error> {
+    let (button, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+    let button = MouseMapper::dq_to_front(button).unwrap_or(0);
+    writeln!(writer, "{},mouse,,{},{},{},{}", ev.timestamp, button, press, ev.position.0, ev.position.1)?;
+    Ok(())
+}
+
+impl KeyboardAction {
+    /// It fails on the first key without `code` in frontend, see `KeyboardAction.to_string`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            write_key_row(&mut writer, index, ev)?;
+        }
+        writeln!(writer, "{},end,,,,,", self.till)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// `mouse` rows are left out. `till` is the timestamp of the last event if there is no `end` row.
+    /// It fails on the first invalid row, `read_csv_lenient` reports all of them.
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<KeyboardAction, Error> {
+        first_error(KeyboardAction::read_csv_lenient(reader)?)
+    }
+
+    /// `read_csv` but the invalid rows are left out and returned beside, `index` counts the rows after the header
+    /// and `reason` is an `Error::Parse` with the line and the column.
+    pub fn read_csv_lenient<R: BufRead>(reader: R) -> Result<(KeyboardAction, Vec<SkippedEvent>), Error> {
+        let (rows, skipped) = read_rows(reader)?;
+        let mut action = KeyboardAction { evs: vec![], till: 0 };
+        let mut till = None;
+        for row in rows {
+            match row {
+                CsvRow::Key(ev) => action.evs.push(ev),
+                CsvRow::Mouse(_) => (),
+                CsvRow::End(timestamp) => till = Some(timestamp)
+            }
+        }
+        action.till = till.unwrap_or_else(|| action.evs.iter().map(|ev| ev.timestamp).max().unwrap_or(0));
+        Ok((action, skipped))
+    }
+}
+
+impl MouseAction {
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for ev in self.evs.iter() {
+            write_mouse_row(&mut writer, ev)?;
+        }
+        writeln!(writer, "{},end,,,,,", self.till)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// `key` rows are left out. `till` is the timestamp of the last event if there is no `end` row.
+    /// It fails on the first invalid row, `read_csv_lenient` reports all of them.
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<MouseAction, Error> {
+        first_error(MouseAction::read_csv_lenient(reader)?)
+    }
+
+    /// see `KeyboardAction::read_csv_lenient`
+    pub fn read_csv_lenient<R: BufRead>(reader: R) -> Result<(MouseAction, Vec<SkippedEvent>), Error> {
+        let (rows, skipped) = read_rows(reader)?;
+        let mut action = MouseAction { evs: vec![], till: 0 };
+        let mut till = None;
+        for row in rows {
+            match row {
+                CsvRow::Mouse(ev) => action.evs.push(ev),
+                CsvRow::Key(_) => (),
+                CsvRow::End(timestamp) => till = Some(timestamp)
+            }
+        }
+        action.till = till.unwrap_or_else(|| action.evs.iter().map(|ev| ev.timestamp).max().unwrap_or(0));
+        Ok((action, skipped))
+    }
+}
+
+impl CombinedAction {
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            match ev {
+                CombinedEv::Keyboard(ev) => write_key_row(&mut writer, index, ev)?,
+                CombinedEv::Mouse(ev) => write_mouse_row(&mut writer, ev)?,
+            }
+        }
+        writeln!(writer, "{},end,,,,,", self.till)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Both `key` and `mouse` rows, in the order of the rows.
+    /// `till` is the timestamp of the last event if there is no `end` row.
+    /// It fails on the first invalid row, `read_csv_lenient` reports all of them.
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<CombinedAction, Error> {
+        first_error(CombinedAction::read_csv_lenient(reader)?)
+    }
+
+    /// see `KeyboardAction::read_csv_lenient`
+    pub fn read_csv_lenient<R: BufRead>(reader: R) -> Result<(CombinedAction, Vec<SkippedEvent>), Error> {
+        let (rows, skipped) = read_rows(reader)?;
+        let mut action = CombinedAction { evs: vec![], till: 0 };
+        let mut till = None;
+        for row in rows {
+            match row {
+                CsvRow::Key(ev) => action.evs.push(CombinedEv::Keyboard(ev)),
+                CsvRow::Mouse(ev) => action.evs.push(CombinedEv::Mouse(ev)),
+                CsvRow::End(timestamp) => till = Some(timestamp)
+            }
+        }
+        action.till = till.unwrap_or_else(|| action.evs.iter().map(|ev| ev.timestamp()).max().unwrap_or(0));
+        Ok((action, skipped))
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use device_query::Keycode;
+    use crate::MouseEventName;
+
+    #[test]
+    fn keyboard_csv() {
+        let action = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::LShift, press: true, timestamp: 10 },
+                KeyboardEv { code: Keycode::LShift, press: false, timestamp: 90 },
+            ],
+            till: 100,
+        };
+        let mut buffer = vec![];
+        action.write_csv(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buffer),
+            "timestamp,type,code,button,press,x,y\n10,key,ShiftLeft,,true,,\n90,key,ShiftLeft,,false,,\n100,end,,,,,\n"
+        );
+
+        let again = KeyboardAction::read_csv(buffer.as_slice()).unwrap();
+        assert_eq!(again.evs.len(), 2);
+        assert_eq!(again.evs[0].code, Keycode::LShift);
+        assert!(!again.evs[1].press);
+        assert_eq!(again.till, 100);
+    }
+
+    #[test]
+    fn mouse_csv() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::RightDown, position: (-5, 20), timestamp: 10 },
+                MouseEv { ev_name: MouseEventName::RightUp, position: (-5, 20), timestamp: 50 },
+            ],
+            till: 60,
+        };
+        let mut buffer = vec![];
+        action.write_csv(&mut buffer).unwrap();
+        assert!(String::from_utf8_lossy(&buffer).contains("\n10,mouse,,2,true,-5,20\n"));
+
+        let again = MouseAction::read_csv(buffer.as_slice()).unwrap();
+        assert!(matches!(again.evs[1].ev_name, MouseEventName::RightUp));
+        assert_eq!(again.evs[1].position, (-5, 20));
+        assert_eq!(again.till, 60);
+    }
+
+    /// errors tell the line and the column of the invalid field
+    #[test]
+    fn csv_row_errors() {
+        let source = "timestamp,type,code,button,press,x,y\n10,key,KeyA,,true,,\n20,key,Nothing,,true,,\n";
+        match KeyboardAction::read_csv(source.as_bytes()) {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((line, column), (3, 3)),
+            _ => panic!("expect a parse error"),
+        }
+
+        let source = "timestamp,type,code,button,press,x,y\n\"10\",\"mouse\",,0,true,1,two\n";
+        match MouseAction::read_csv(source.as_bytes()) {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((line, column), (2, 7)),
+            _ => panic!("expect a parse error"),
+        }
+
+        assert!(matches!(MouseAction::read_csv("time,x,y\n".as_bytes()), Err(Error::Parse { line: 1, .. })));
+    }
+
+    /// every invalid row is returned with its line and column
+    #[test]
+    fn csv_lenient() {
+        let source = "timestamp,type,code,button,press,x,y\n10,key,Nothing,,true,,\n\n20,key,KeyA,,true,,\n30,key,KeyA,,maybe,,\n";
+        let (action, skipped) = KeyboardAction::read_csv_lenient(source.as_bytes()).unwrap();
+        assert_eq!(action.evs.len(), 1);
+        let found: Vec<_> = skipped.iter().map(|skipped| match skipped.reason {
+            Error::Parse { line, column, .. } => (skipped.index, line, column),
+            _ => panic!("expect a parse error"),
+        }).collect();
+        assert_eq!(found, vec![(0, 2, 3), (2, 5, 5)]);
+    }
+}
+// endregion
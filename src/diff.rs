@@ -0,0 +1,330 @@
+use std::fmt;
+use crate::{KeyboardAction, KeyboardEv, KeyboardMapper, MouseAction, MouseEv, MouseMapper};
+use crate::timeline::TimedEvent;
+
+// region diff
+/// a step of `ActionDiff`, the indexes are of the events in the old and the new action
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffOp {
+    /// the same event in both, within the tolerance
+    Same { old: usize, new: usize },
+    /// only in the old action
+    Removed { old: usize },
+    /// only in the new action
+    Inserted { new: usize },
+    /// the same key or button in both, but the timestamp moved by more than the tolerance (`time_delta`, new - old)
+    /// or the position moved (`position_delta`, new - old, mouse only)
+    Changed { old: usize, new: usize, time_delta: i64, position_delta: Option<(i32, i32)> },
+}
+
+/// the difference of two actions, see `KeyboardAction.diff`
+pub struct ActionDiff {
+    pub ops: Vec<DiffOp>,
+    /// a line per event for the text form
+    old_lines: Vec<String>,
+    new_lines: Vec<String>,
+}
+
+impl ActionDiff {
+    /// if the actions are the same within the tolerance
+    pub fn is_same(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, DiffOp::Same { .. }))
+    }
+
+    /// Render as a unified diff with `context` unchanged events around each change, one event per line, e.g.
+    /// ```text
+    /// --- old
+    /// +++ new
+    /// @@ -1,2 +1,2 @@
+    ///  100ms press KeyA
+    /// -200ms release KeyA
+    /// +260ms release KeyA
+    /// ```
+    /// It is empty if the actions are the same.
+    pub fn unified(&self, context: usize) -> String {
+        if self.is_same() {
+            return String::new();
+        }
+
+        // the ranges of ops to show, a change with its context, merged when they touch
+        let mut ranges: Vec<(usize, usize)> = vec![];
+        for (index, op) in self.ops.iter().enumerate() {
+            if matches!(op, DiffOp::Same { .. }) {
+                continue;
+            }
+            let (from, to) = (index.saturating_sub(context), (index + context + 1).min(self.ops.len()));
+            match ranges.last_mut() {
+                Some(last) if from <= last.1 => last.1 = to,
+                _ => ranges.push((from, to)),
+            }
+        }
+
+        let mut text = String::from("--- old\n+++ new\n");
+        for (from, to) in ranges {
+            // the lines of each side before the range
+            let (old_before, new_before) = self.ops[..from].iter().fold((0, 0), |(old, new), op| {
+                let (old_lines, new_lines) = op_lines(op);
+                (old + old_lines, new + new_lines)
+            });
+            let (old_count, new_count) = self.ops[from..to].iter().fold((0, 0), |(old, new), op| {
+                let (old_lines, new_lines) = op_lines(op);
+                (old + old_lines, new + new_lines)
+            });
+            // as diff does, an empty side starts at the line before
+            let start = |before: usize, count: usize| if count == 0 { before } else { before + 1 };
+            text.push_str(&format!("@@ -{},{} +{},{} @@\n",
+                                   start(old_before, old_count), old_count, start(new_before, new_count), new_count));
+
+            for op in self.ops[from..to].iter() {
+                match op {
+                    DiffOp::Same { old, .. } => text.push_str(&format!(" {}\n", self.old_lines[*old])),
+                    DiffOp::Removed { old } => text.push_str(&format!("-{}\n", self.old_lines[*old])),
+                    DiffOp::Inserted { new } => text.push_str(&format!("+{}\n", self.new_lines[*new])),
+                    DiffOp::Changed { old, new, .. } => {
+                        text.push_str(&format!("-{}\n", self.old_lines[*old]));
+                        text.push_str(&format!("+{}\n", self.new_lines[*new]));
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+/// the unified diff with 3 events of context
+impl fmt::Display for ActionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.unified(3))
+    }
+}
+
+/// how many lines of the old and the new action an op is
+fn op_lines(op: &DiffOp) -> (usize, usize) {
+    match op {
+        DiffOp::Same { .. } | DiffOp::Changed { .. } => (1, 1),
+        DiffOp::Removed { .. } => (1, 0),
+        DiffOp::Inserted { .. } => (0, 1),
+    }
+}
+
+/// Align the events by the longest common subsequence of their keys (and press or release),
+/// then compare the timestamps and the positions of the aligned ones.
+fn diff_events<E, P>(old: &[E], new: &[E], tolerance: u64, position: P) -> Vec<DiffOp>
+    where E: TimedEvent, P: Fn(&E) -> Option<(i32, i32)> {
+    let old_keys: Vec<(E::Key, bool)> = old.iter().map(|ev| ev.key()).collect();
+    let new_keys: Vec<(E::Key, bool)> = new.iter().map(|ev| ev.key()).collect();
+    let (n, m) = (old.len(), new.len());
+
+    // the common prefix and suffix are aligned as they are, only the middle is searched
+    let prefix = old_keys.iter().zip(new_keys.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = old_keys[prefix..].iter().rev().zip(new_keys[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    lcs_pairs(&old_keys[prefix..n - suffix], &new_keys[prefix..m - suffix], (prefix, prefix), &mut pairs);
+    pairs.extend((0..suffix).map(|k| (n - suffix + k, m - suffix + k)));
+
+    let mut ops = vec![];
+    let (mut i, mut j) = (0, 0);
+    for (old_index, new_index) in pairs {
+        // the removals first, as diff does
+        ops.extend((i..old_index).map(|old| DiffOp::Removed { old }));
+        ops.extend((j..new_index).map(|new| DiffOp::Inserted { new }));
+
+        let time_delta = new[new_index].timestamp() as i64 - old[old_index].timestamp() as i64;
+        let position_delta = match (position(&old[old_index]), position(&new[new_index])) {
+            (Some(a), Some(b)) if a != b => Some((b.0 - a.0, b.1 - a.1)),
+            _ => None
+        };
+        ops.push(match time_delta.unsigned_abs() > tolerance || position_delta.is_some() {
+            true => DiffOp::Changed { old: old_index, new: new_index, time_delta, position_delta },
+            false => DiffOp::Same { old: old_index, new: new_index },
+        });
+        i = old_index + 1;
+        j = new_index + 1;
+    }
+    ops.extend((i..n).map(|old| DiffOp::Removed { old }));
+    ops.extend((j..m).map(|new| DiffOp::Inserted { new }));
+    ops
+}
+
+/// Push the indexes (plus `offset`) of a longest common subsequence of `old` and `new`, in order.
+/// Hirschberg's way, in linear space: split `old` in halves, find where the subsequence crosses `new`
+/// at the split, and go on with both sides.
+fn lcs_pairs<T: PartialEq>(old: &[T], new: &[T], offset: (usize, usize), pairs: &mut Vec<(usize, usize)>) {
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+    if old.len() == 1 {
+        if let Some(j) = new.iter().position(|key| *key == old[0]) {
+            pairs.push((offset.0, offset.1 + j));
+        }
+        return;
+    }
+
+    let middle = old.len() / 2;
+    let forward = lcs_lengths(&old[..middle], new, false);
+    let backward = lcs_lengths(&old[middle..], new, true);
+    let m = new.len();
+    // the first best split, so that the subsequence is as early in `new` as it can
+    let split = (0..=m).rev().max_by_key(|&j| forward[j] + backward[m - j]).unwrap_or(0);
+
+    lcs_pairs(&old[..middle], &new[..split], offset, pairs);
+    lcs_pairs(&old[middle..], &new[split..], (offset.0 + middle, offset.1 + split), pairs);
+}
+
+/// `lengths[j]`: length of the longest common subsequence of `old` and the first `j` items of `new`,
+/// or the last `j` items if `reversed`
+fn lcs_lengths<T: PartialEq>(old: &[T], new: &[T], reversed: bool) -> Vec<u32> {
+    let (n, m) = (old.len(), new.len());
+    let mut lengths = vec![0u32; m + 1];
+    for i in 0..n {
+        let a = &old[if reversed { n - 1 - i } else { i }];
+        // `lengths[j - 1]` of the row before
+        let mut diagonal = 0;
+        for j in 1..=m {
+            let above = lengths[j];
+            lengths[j] = match *a == new[if reversed { m - j } else { j - 1 }] {
+                true => diagonal + 1,
+                false => above.max(lengths[j - 1])
+            };
+            diagonal = above;
+        }
+    }
+    lengths
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    /// Compare with `other`, a newer take of the same macro.
+    /// The events are aligned by key and press, an aligned event whose timestamp moved by more than `tolerance` ms
+    /// is `Changed`. The timestamps are compared as they are, `normalize` both actions first to ignore a delay
+    /// before the first event.
+    pub fn diff(&self, other: &KeyboardAction, tolerance: u64) -> ActionDiff {
+        let line = |ev: &KeyboardEv| {
+            let code = KeyboardMapper::dq_to_front(ev.code).map(String::from).unwrap_or_else(|| format!("{:?}", ev.code));
+            format!("{}ms {} {}", ev.timestamp, if ev.press { "press" } else { "release" }, code)
+        };
+        ActionDiff {
+            ops: diff_events(&self.evs, &other.evs, tolerance, |_| None),
+            old_lines: self.evs.iter().map(line).collect(),
+            new_lines: other.evs.iter().map(line).collect(),
+        }
+    }
+}
+
+impl MouseAction {
+    /// see `KeyboardAction.diff`, the events are aligned by button and press, and an aligned event whose position
+    /// moved is `Changed` too.
+    pub fn diff(&self, other: &MouseAction, tolerance: u64) -> ActionDiff {
+        let line = |ev: &MouseEv| {
+            let (button, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+            let button = match button {
+                1 => "left",
+                2 => "right",
+                _ => "middle",
+            };
+            format!("{}ms {} {} at {},{}", ev.timestamp, if press { "down" } else { "up" }, button, ev.position.0, ev.position.1)
+        };
+        ActionDiff {
+            ops: diff_events(&self.evs, &other.evs, tolerance, |ev| Some(ev.position)),
+            old_lines: self.evs.iter().map(line).collect(),
+            new_lines: other.evs.iter().map(line).collect(),
+        }
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use device_query::Keycode;
+    use crate::MouseEventName;
+    use super::*;
+
+    fn tap(code: Keycode, timestamp: u64) -> Vec<KeyboardEv> {
+        vec![
+            KeyboardEv { code, press: true, timestamp },
+            KeyboardEv { code, press: false, timestamp: timestamp + 50 },
+        ]
+    }
+
+    #[test]
+    fn keyboard_diff() {
+        let old = KeyboardAction { evs: [tap(Keycode::A, 0), tap(Keycode::B, 100), tap(Keycode::C, 200)].concat(), till: 300 };
+        let new = KeyboardAction { evs: [tap(Keycode::A, 0), tap(Keycode::C, 210), tap(Keycode::D, 400)].concat(), till: 500 };
+
+        let diff = old.diff(&new, 20);
+        assert!(!diff.is_same());
+        assert_eq!(diff.ops, vec![
+            DiffOp::Same { old: 0, new: 0 },
+            DiffOp::Same { old: 1, new: 1 },
+            DiffOp::Removed { old: 2 },
+            DiffOp::Removed { old: 3 },
+            DiffOp::Same { old: 4, new: 2 },
+            DiffOp::Same { old: 5, new: 3 },
+            DiffOp::Inserted { new: 4 },
+            DiffOp::Inserted { new: 5 },
+        ]);
+        assert_eq!(diff.unified(1), "\
+--- old
++++ new
+@@ -2,5 +2,5 @@
+ 50ms release KeyA
+-100ms press KeyB
+-150ms release KeyB
+ 200ms press KeyC
+ 250ms release KeyC
++400ms press KeyD
++450ms release KeyD
+");
+
+        let diff = old.diff(&new, 5);
+        assert!(matches!(diff.ops[4], DiffOp::Changed { time_delta: 10, position_delta: None, .. }));
+        assert!(old.diff(&old, 0).is_same());
+        assert_eq!(old.diff(&old, 0).to_string(), "");
+    }
+
+    #[test]
+    fn linear_lcs() {
+        let (old, new): (Vec<char>, Vec<char>) = ("ABCBDAB".chars().collect(), "BDCABA".chars().collect());
+        let mut pairs = vec![];
+        lcs_pairs(&old, &new, (0, 0), &mut pairs);
+        assert_eq!(pairs.len(), 4);
+        assert!(pairs.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+        assert!(pairs.iter().all(|&(i, j)| old[i] == new[j]));
+
+        // a long action with one change in the middle
+        let evs: Vec<KeyboardEv> = (0..1000).flat_map(|i| tap(if i % 2 == 0 { Keycode::A } else { Keycode::B }, i * 100)).collect();
+        let old = KeyboardAction { evs: evs.clone(), till: 100000 };
+        let mut new = KeyboardAction { evs, till: 100000 };
+        new.evs[1000].code = Keycode::C;
+        let ops = old.diff(&new, 0).ops;
+        assert_eq!(ops.iter().filter(|op| !matches!(op, DiffOp::Same { .. })).count(), 2);
+        assert_eq!(ops[1000], DiffOp::Removed { old: 1000 });
+    }
+
+    #[test]
+    fn mouse_diff() {
+        let old = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (10, 10), timestamp: 0 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (10, 10), timestamp: 80 },
+            ],
+            till: 100,
+        };
+        let mut new = MouseAction { evs: old.evs.clone(), till: old.till };
+        new.evs[1].position = (15, 8);
+
+        let diff = old.diff(&new, 0);
+        assert_eq!(diff.ops[1], DiffOp::Changed { old: 1, new: 1, time_delta: 0, position_delta: Some((5, -2)) });
+        assert_eq!(diff.to_string(), "\
+--- old
++++ new
+@@ -1,2 +1,2 @@
+ 0ms down left at 10,10
+-80ms up left at 10,10
++80ms up left at 15,8
+");
+    }
+}
+// endregion
@@ -1,6 +1,25 @@
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
-use enigo::{Enigo, KeyboardControllable, MouseControllable};
-use crate::{KeyboardMapper, MouseEv, KeyboardAction, KeyboardEv, utils::set_timeout, MouseAction, MouseMapper};
+use device_query::Keycode;
+use enigo::{Enigo, Key, KeyboardControllable, MouseControllable};
+use crate::{MouseEv, KeyboardAction, KeyboardEv, utils::set_timeout, MouseAction, MouseMapper, KeyMap, PlaybackMode, Error, Severity, GestureOptions, MouseEventName, CombinedAction, CombinedEv};
+use crate::gesture::{recognize, semantic_events};
+
+// region simulator
+/// Run a call of the simulator. `Enigo` panics on what it can not simulate (e.g. no display to connect to),
+/// the panic is returned as `Error::Backend`.
+fn simulate<F: FnOnce()>(call: F) -> Result<(), Error> {
+    catch_unwind(AssertUnwindSafe(call)).map_err(|payload| {
+        let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (None, Some(message)) => message.clone(),
+            (None, None) => String::from("the simulator panicked")
+        };
+        Error::Backend(message)
+    })
+}
+// endregion
 
 // region keyboard event player
 pub struct KeyboardPlayer {
@@ -12,6 +31,14 @@ pub struct KeyboardPlayer {
     duration: u64,
     /// events in the action
     ev_queue: Vec<KeyboardEv>,
+    /// collapse left/right modifiers and type numpad keys as characters
+    compatible: bool,
+    /// translate or suppress keys before they are played
+    keymap: KeyMap,
+    /// how the keys are turned into keys for the simulator
+    mode: PlaybackMode,
+    /// refuse to load the actions with errors (see `KeyboardAction.validate`)
+    strict: bool,
 }
 
 impl KeyboardPlayer {
@@ -21,14 +48,51 @@ impl KeyboardPlayer {
             playing: Arc::new(Mutex::new(false)),
             duration: 0,
             ev_queue: vec![],
+            compatible: false,
+            keymap: KeyMap::new(),
+            mode: PlaybackMode::Direct,
+            strict: false,
         }
     }
 
+    /// Refuse to load the actions with `Severity::Error` diagnostics (see `KeyboardAction.validate`),
+    /// so that e.g. a key pressed and never released does not stay held in the OS.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// choose between physical-position and character playback, see `PlaybackMode`.
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+    }
+
+    /// translate or suppress keys before they are played.
+    pub fn set_keymap(&mut self, keymap: KeyMap) {
+        self.keymap = keymap;
+    }
+
+    /// Switch to the compatible mode (see `KeyboardMapper::dq_to_enigo_compatible`).
+    /// By default left/right modifiers and numpad keys are played with their own keycodes.
+    pub fn set_compatible(&mut self, compatible: bool) {
+        self.compatible = compatible;
+    }
+
+    /// `Keycode` => `Key` in `Enigo` under the current mode
+    fn map_key(&self, code: Keycode, shift: bool) -> Option<Key> {
+        self.mode.to_enigo(self.keymap.translate(code)?, shift, self.compatible)
+    }
+
     /// load an action record to play later.
-    pub fn load(&mut self, action: KeyboardAction) -> Result<(), ()> {
+    pub fn load(&mut self, action: KeyboardAction) -> Result<(), Error> {
         return if *self.playing.lock().unwrap() {
-            Err(())
+            Err(Error::Busy)
         } else {
+            if self.strict {
+                let errors: Vec<_> = action.validate().into_iter().filter(|d| d.severity == Severity::Error).collect();
+                if !errors.is_empty() {
+                    return Err(Error::Invalid(errors));
+                }
+            }
             self.ev_queue = action.evs;
             self.duration = action.till;
             Ok(())
@@ -36,39 +100,28 @@ impl KeyboardPlayer {
     }
 
     /// auto-play keyboard event using simulator.
-    pub fn do_play(&mut self) -> Result<(), ()> {
+    pub fn do_play(&mut self) -> Result<(), Error> {
         return if *self.playing.lock().unwrap() {
-            Err(())
+            Err(Error::Busy)
         } else {
             *self.playing.lock().unwrap() = true;
             let mut last_act_time = 0;
+            let mut pressed: HashMap<Keycode, Option<Key>> = HashMap::new();
             if self.ev_queue.len() > 0 && self.duration > 0 {
                 for ev in self.ev_queue.iter() {
+                    let key = self.key_of(&mut pressed, ev);
+                    let mut sent = Ok(());
                     if ev.timestamp <= last_act_time {
-                        match (KeyboardMapper::dq_to_enigo(ev.code), ev.press) {
-                            (Some(key), is_press) => {
-                                if is_press {
-                                    self.instance.key_down(key);
-                                } else {
-                                    self.instance.key_up(key);
-                                }
-                            }
-                            (None, _) => ()
-                        }
+                        sent = Self::send_key(&mut self.instance, key, ev.press);
                     } else {
                         set_timeout(|| {
-                            match (KeyboardMapper::dq_to_enigo(ev.code), ev.press) {
-                                (Some(key), is_press) => {
-                                    if is_press {
-                                        self.instance.key_down(key);
-                                    } else {
-                                        self.instance.key_up(key);
-                                    }
-                                }
-                                (None, _) => ()
-                            }
+                            sent = Self::send_key(&mut self.instance, key, ev.press);
                         }, ev.timestamp - last_act_time);
                     }
+                    if sent.is_err() {
+                        *self.playing.lock().unwrap() = false;
+                        return sent;
+                    }
 
                     last_act_time = ev.timestamp;
                 }
@@ -78,6 +131,31 @@ impl KeyboardPlayer {
         };
     }
 
+    /// `Key` in `Enigo` of an event, `pressed` holds the keys sent for the pressed keys, so that they are released
+    /// with the same key even if the mapping changed in between (e.g. `Shift` in `PlaybackMode::Character`)
+    fn key_of(&self, pressed: &mut HashMap<Keycode, Option<Key>>, ev: &KeyboardEv) -> Option<Key> {
+        let shift = pressed.contains_key(&Keycode::LShift) || pressed.contains_key(&Keycode::RShift);
+        if ev.press {
+            let key = self.map_key(ev.code, shift);
+            pressed.insert(ev.code, key);
+            key
+        } else {
+            match pressed.remove(&ev.code) {
+                Some(key) => key,
+                None => self.map_key(ev.code, shift)
+            }
+        }
+    }
+
+    /// press or release `key` with the simulator, `None` is not played
+    fn send_key(instance: &mut Enigo, key: Option<Key>, press: bool) -> Result<(), Error> {
+        match key {
+            Some(key) if press => simulate(|| instance.key_down(key)),
+            Some(key) => simulate(|| instance.key_up(key)),
+            None => Ok(())
+        }
+    }
+
     pub fn get_record(&self) -> Vec<KeyboardEv> {
         self.ev_queue.clone()
     }
@@ -98,6 +176,12 @@ pub struct MousePlayer {
     duration: u64,
     /// events in the action
     ev_queue: Vec<MouseEv>,
+    /// refuse to load the actions with errors (see `MouseAction.validate`)
+    strict: bool,
+    /// `[width, height]` to check the positions against in strict mode
+    screen_size: Option<[u32; 2]>,
+    /// replay the gestures as intents instead of the events (see `MouseAction.semantic_events`)
+    semantic: Option<GestureOptions>,
 }
 
 impl MousePlayer {
@@ -107,14 +191,40 @@ impl MousePlayer {
             playing: Arc::new(Mutex::new(false)),
             duration: 0,
             ev_queue: vec![],
+            strict: false,
+            screen_size: None,
+            semantic: None,
         }
     }
 
+    /// Refuse to load the actions with `Severity::Error` diagnostics (see `MouseAction.validate`),
+    /// positions are checked too if `screen_size` is given.
+    pub fn set_strict(&mut self, strict: bool, screen_size: Option<[u32; 2]>) {
+        self.strict = strict;
+        self.screen_size = screen_size;
+    }
+
+    /// Replay the gestures of the actions as intents (see `MouseAction.semantic_events`), e.g. a drag along a smooth
+    /// path instead of a jump from the press to the release. `None` replays the events as recorded.
+    pub fn set_semantic(&mut self, options: Option<GestureOptions>) {
+        self.semantic = options;
+    }
+
     /// load an action record to play later.
-    pub fn load(&mut self, action: MouseAction) -> Result<(), ()> {
+    pub fn load(&mut self, action: MouseAction) -> Result<(), Error> {
         return if *self.playing.lock().unwrap() {
-            Err(())
+            Err(Error::Busy)
         } else {
+            if self.strict {
+                let diagnostics = match self.screen_size {
+                    Some(screen) => action.validate_on_screen(screen),
+                    None => action.validate()
+                };
+                let errors: Vec<_> = diagnostics.into_iter().filter(|d| d.severity == Severity::Error).collect();
+                if !errors.is_empty() {
+                    return Err(Error::Invalid(errors));
+                }
+            }
             self.ev_queue = action.evs;
             self.duration = action.till;
             Ok(())
@@ -122,51 +232,174 @@ impl MousePlayer {
     }
 
     /// auto-play keyboard event using simulator.
-    pub fn do_play(&mut self) -> Result<(), ()> {
+    pub fn do_play(&mut self) -> Result<(), Error> {
         return if *self.playing.lock().unwrap() {
-            Err(())
+            Err(Error::Busy)
         } else {
             *self.playing.lock().unwrap() = true;
-            let mut last_act_time = 0;
-            if self.ev_queue.len() > 0 && self.duration > 0 {
-                for ev in self.ev_queue.iter() {
-                    if ev.timestamp <= last_act_time {
-                        match MouseMapper::parse_ev_name(ev.ev_name) {
-                            (enigo_button, true) => {
-                                let (x, y) = ev.position;
-                                self.instance.mouse_move_to(x, y);
-                                self.instance.mouse_down(enigo_button);
-                            }
-                            (enigo_button, false) => {
-                                let (x, y) = ev.position;
-                                self.instance.mouse_move_to(x, y);
-                                self.instance.mouse_up(enigo_button);
-                            }
-                        }
-                    } else {
-                        set_timeout(|| {
-                            match MouseMapper::parse_ev_name(ev.ev_name) {
-                                (enigo_button, true) => {
-                                    let (x, y) = ev.position;
-                                    self.instance.mouse_move_to(x, y);
-                                    self.instance.mouse_down(enigo_button);
-                                }
-                                (enigo_button, false) => {
-                                    let (x, y) = ev.position;
-                                    self.instance.mouse_move_to(x, y);
-                                    self.instance.mouse_up(enigo_button);
-                                }
-                            }
-                        }, ev.timestamp - last_act_time);
-                    }
+            let result = match self.semantic {
+                Some(options) => self.play_semantic(&options),
+                None => self.play_queue()
+            };
+            *self.playing.lock().unwrap() = false;
+            result
+        };
+    }
 
-                    last_act_time = ev.timestamp;
+    /// play the events of the queue as recorded, stops at the first failure of the simulator
+    fn play_queue(&mut self) -> Result<(), Error> {
+        let mut last_act_time = 0;
+        if !self.ev_queue.is_empty() && self.duration > 0 {
+            for ev in self.ev_queue.iter() {
+                let mut sent = Ok(());
+                if ev.timestamp <= last_act_time {
+                    sent = Self::send_mouse(&mut self.instance, Some(ev.ev_name), ev.position);
+                } else {
+                    set_timeout(|| {
+                        sent = Self::send_mouse(&mut self.instance, Some(ev.ev_name), ev.position);
+                    }, ev.timestamp - last_act_time);
                 }
+                sent?;
+
+                last_act_time = ev.timestamp;
             }
+        }
+        Ok(())
+    }
+
+    /// play the semantic events of the queue, see `set_semantic`
+    fn play_semantic(&mut self, options: &GestureOptions) -> Result<(), Error> {
+        let evs = semantic_events(&recognize(&self.ev_queue, options), options.drag_steps);
+        let mut last_act_time = 0;
+        for ev in evs.iter() {
+            let mut sent = Ok(());
+            set_timeout(|| {
+                sent = Self::send_mouse(&mut self.instance, ev.ev_name, ev.position);
+            }, ev.timestamp.saturating_sub(last_act_time));
+            sent?;
+            last_act_time = last_act_time.max(ev.timestamp);
+        }
+        Ok(())
+    }
+
+    /// move to `position` and press or release the button of `ev_name` with the simulator, `None` only moves
+    fn send_mouse(instance: &mut Enigo, ev_name: Option<MouseEventName>, position: (i32, i32)) -> Result<(), Error> {
+        simulate(|| {
+            let (x, y) = position;
+            instance.mouse_move_to(x, y);
+            match ev_name.map(MouseMapper::parse_ev_name) {
+                Some((enigo_button, true)) => instance.mouse_down(enigo_button),
+                Some((enigo_button, false)) => instance.mouse_up(enigo_button),
+                None => ()
+            }
+        })
+    }
+}
+// endregion
+
+// region combined event player
+pub struct CombinedPlayer {
+    /// maps the keys and holds the simulator, see `set_mode`, `set_keymap` and `set_compatible`
+    keyboard: KeyboardPlayer,
+    /// if is playing
+    playing: Arc<Mutex<bool>>,
+    /// duration of the action
+    duration: u64,
+    /// keyboard and mouse events in the order of the timeline
+    ev_queue: Vec<CombinedEv>,
+}
+
+impl CombinedPlayer {
+    pub fn new() -> CombinedPlayer {
+        CombinedPlayer {
+            keyboard: KeyboardPlayer::new(),
+            playing: Arc::new(Mutex::new(false)),
+            duration: 0,
+            ev_queue: vec![],
+        }
+    }
+
+    /// see `KeyboardPlayer.set_mode`
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.keyboard.set_mode(mode);
+    }
+
+    /// see `KeyboardPlayer.set_keymap`
+    pub fn set_keymap(&mut self, keymap: KeyMap) {
+        self.keyboard.set_keymap(keymap);
+    }
+
+    /// see `KeyboardPlayer.set_compatible`
+    pub fn set_compatible(&mut self, compatible: bool) {
+        self.keyboard.set_compatible(compatible);
+    }
+
+    /// load an action record to play later.
+    pub fn load(&mut self, action: CombinedAction) -> Result<(), Error> {
+        if *self.playing.lock().unwrap() {
+            return Err(Error::Busy);
+        }
+        self.ev_queue = action.evs;
+        self.duration = action.till;
+        Ok(())
+    }
+
+    /// auto-play keyboard and mouse events in turn using simulator, e.g. a shift-click.
+    pub fn do_play(&mut self) -> Result<(), Error> {
+        return if *self.playing.lock().unwrap() {
+            Err(Error::Busy)
+        } else {
+            *self.playing.lock().unwrap() = true;
+            let result = self.play_queue();
             *self.playing.lock().unwrap() = false;
-            Ok(())
+            result
         };
     }
+
+    /// play the events of the queue, stops at the first failure of the simulator
+    fn play_queue(&mut self) -> Result<(), Error> {
+        let mut last_act_time = 0;
+        let mut pressed: HashMap<Keycode, Option<Key>> = HashMap::new();
+        if !self.ev_queue.is_empty() && self.duration > 0 {
+            for ev in self.ev_queue.iter() {
+                let key = match ev {
+                    CombinedEv::Keyboard(ev) => self.keyboard.key_of(&mut pressed, ev),
+                    CombinedEv::Mouse(_) => None
+                };
+                let instance = &mut self.keyboard.instance;
+                let mut send = || match ev {
+                    CombinedEv::Keyboard(ev) => KeyboardPlayer::send_key(instance, key, ev.press),
+                    CombinedEv::Mouse(ev) => MousePlayer::send_mouse(instance, Some(ev.ev_name), ev.position),
+                };
+                let mut sent = Ok(());
+                if ev.timestamp() <= last_act_time {
+                    sent = send();
+                } else {
+                    set_timeout(|| {
+                        sent = send();
+                    }, ev.timestamp() - last_act_time);
+                }
+                sent?;
+
+                last_act_time = ev.timestamp();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_record(&self) -> Vec<CombinedEv> {
+        self.ev_queue.clone()
+    }
+
+    pub fn get_duration(&self) -> u64 {
+        self.duration
+    }
+}
+
+impl Default for CombinedPlayer {
+    fn default() -> Self {
+        CombinedPlayer::new()
+    }
 }
 // endregion
 
@@ -176,7 +409,16 @@ mod test {
     use super::*;
     use device_query::Keycode;
     use enigo::Key;
-    use crate::MouseEventName;
+
+    /// 模拟器 panic 时返回 `Error::Backend`, 而不是让播放器一直处于播放状态
+    #[test]
+    fn simulate_panic() {
+        assert!(simulate(|| ()).is_ok());
+        match simulate(|| panic!("no display")) {
+            Err(Error::Backend(message)) => assert_eq!(message, "no display"),
+            _ => panic!("expect a backend error"),
+        }
+    }
 
     /// **pass** 0-9 a-z
     #[test]
@@ -491,5 +733,18 @@ mod test {
             Err(_) => ()
         }
     }
+
+    /// shift-click: 键盘和鼠标事件按时间线交替播放
+    #[test]
+    fn display_combined() {
+        let keyboard = KeyboardAction::from_string("{\"evs\":[{\"code\":\"ShiftLeft\",\"press\":true,\"timestamp\":0},{\"code\":\"ShiftLeft\",\"press\":false,\"timestamp\":300}],\"till\":400}").unwrap();
+        let mouse = MouseAction::from_string("{\"evs\":[{\"ev_name\":1,\"position\":[200,200],\"timestamp\":100},{\"ev_name\":2,\"position\":[200,200],\"timestamp\":200}],\"till\":400}").unwrap();
+
+        let mut player = CombinedPlayer::new();
+        player.load(CombinedAction::merge(&keyboard, &mouse, 0)).unwrap();
+        assert_eq!(player.get_record().len(), 4);
+        assert_eq!(player.get_duration(), 400);
+        assert!(player.do_play().is_ok());
+    }
 }
 // endregion
\ No newline at end of file
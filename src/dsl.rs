@@ -0,0 +1,478 @@
+use std::collections::HashSet;
+use device_query::Keycode;
+use crate::{Error, KeyboardAction, KeyboardEv, KeyboardMapper, MouseAction, MouseEv, MouseEventName, MouseMapper, TypingProfile};
+use crate::text::{typed_char, DEFAULT_DWELL, DEFAULT_FLIGHT};
+
+// region names
+/// key names of the macro language: `code` in frontend (e.g. `KeyA`, `ShiftLeft`),
+/// or the short names of the modifiers (e.g. `LShift`, `Ctrl`)
+fn parse_key(name: &str) -> Option<Keycode> {
+    match name {
+        "Shift" | "LShift" => Some(Keycode::LShift),
+        "RShift" => Some(Keycode::RShift),
+        "Ctrl" | "Control" | "LCtrl" | "LControl" => Some(Keycode::LControl),
+        "RCtrl" | "RControl" => Some(Keycode::RControl),
+        "Alt" | "LAlt" => Some(Keycode::LAlt),
+        "RAlt" => Some(Keycode::RAlt),
+        "Meta" | "Super" | "Win" => Some(Keycode::Meta),
+        _ => KeyboardMapper::front_to_dq(name)
+    }
+}
+
+fn parse_button(name: &str, press: bool) -> Option<MouseEventName> {
+    let button = match name {
+        "left" => 1,
+        "right" => 2,
+        "middle" => 3,
+        _ => return None
+    };
+    MouseMapper::dq_to_ev_name(button, press)
+}
+
+fn button_name(ev_name: MouseEventName) -> &'static str {
+    match MouseMapper::ev_name_to_dq(ev_name).0 {
+        2 => "right",
+        3 => "middle",
+        _ => "left"
+    }
+}
+
+/// `200ms`, `1.5s` => milliseconds
+fn parse_duration(duration: &str) -> Option<u64> {
+    let (number, scale) = match duration.strip_suffix("ms") {
+        Some(number) => (number, 1.0),
+        None => (duration.strip_suffix('s')?, 1000.0)
+    };
+    match number.parse::<f64>() {
+        Ok(number) if number >= 0.0 && number.is_finite() => Some((number * scale).round() as u64),
+        _ => None
+    }
+}
+// endregion
+
+// region parser
+/// Parse the macro language, one command per line:
+/// ```text
+/// # comment
+/// wait 200ms              (or `wait 1.5s`)
+/// press LShift            (key names are `code` in frontend, or `Shift`/`LShift`/`Ctrl`/`RAlt`/`Meta`...)
+/// tap KeyA                (press and release at once)
+/// tap KeyA hold 80ms      (release after the time, the time goes on while holding)
+/// release LShift
+/// type "hello\n"          (typed with `TypingProfile::default()`, the time goes on while typing)
+/// click left at 100,200   (`left`, `right` or `middle`)
+/// down left at 100,200
+/// up left at 300,200
+/// release all             (keys and buttons still held)
+/// ```
+/// `till` of both actions is the time at the end of the script.
+pub fn parse_dsl(script: &str) -> Result<(KeyboardAction, MouseAction), Error> {
+    let mut keyboard = KeyboardAction { evs: vec![], till: 0 };
+    let mut mouse = MouseAction { evs: vec![], till: 0 };
+    let mut now: u64 = 0;
+    // held keys and buttons, in the order they are pressed
+    let mut keys_held: Vec<Keycode> = vec![];
+    let mut buttons_held: Vec<(MouseEventName, (i32, i32))> = vec![];
+
+    for (index, line) in script.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |column: usize, message: String| Error::Parse { line: line_number, column, message };
+
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (command, rest) = match line.find(char::is_whitespace) {
+            Some(end) => (&line[..end], line[end..].trim_start()),
+            None => (line, "")
+        };
+        // column (from 1) of the argument
+        let column = indent + line.len() - rest.len() + 1;
+
+        match command {
+            "wait" => {
+                now += parse_duration(rest).ok_or_else(|| error(column, format!("expect a duration like `200ms`, found `{}`", rest)))?;
+            }
+            "press" => {
+                let code = parse_key(rest).ok_or_else(|| error(column, format!("unknown key `{}`", rest)))?;
+                keyboard.evs.push(KeyboardEv { code, press: true, timestamp: now });
+                keys_held.push(code);
+            }
+            "tap" => {
+                let (key, hold) = match rest.split_once(" hold ") {
+                    Some((key, hold)) => {
+                        let hold = hold.trim();
+                        let hold_column = column + rest.len() - hold.len();
+                        (key.trim(), parse_duration(hold).ok_or_else(|| error(hold_column, format!("expect a duration like `80ms`, found `{}`", hold)))?)
+                    }
+                    None => (rest, 0)
+                };
+                let code = parse_key(key).ok_or_else(|| error(column, format!("unknown key `{}`", key)))?;
+                keyboard.evs.push(KeyboardEv { code, press: true, timestamp: now });
+                now += hold;
+                keyboard.evs.push(KeyboardEv { code, press: false, timestamp: now });
+            }
+            "release" if rest == "all" => {
+                for code in keys_held.drain(..).rev() {
+                    keyboard.evs.push(KeyboardEv { code, press: false, timestamp: now });
+                }
+                for (ev_name, position) in buttons_held.drain(..).rev() {
+                    let (button, _) = MouseMapper::ev_name_to_dq(ev_name);
+                    let ev_name = MouseMapper::dq_to_ev_name(button, false).unwrap_or(ev_name);
+                    mouse.evs.push(MouseEv { ev_name, position, timestamp: now });
+                }
+            }
+            "release" => {
+                let code = parse_key(rest).ok_or_else(|| error(column, format!("unknown key `{}`", rest)))?;
+                keyboard.evs.push(KeyboardEv { code, press: false, timestamp: now });
+                keys_held.retain(|held| *held != code);
+            }
+            "type" => {
+                let text = parse_string(rest).ok_or_else(|| error(column, String::from("expect a quoted text")))?;
+                let typed = KeyboardAction::from_text(&text, TypingProfile::default()).map_err(|err| match err {
+                    Error::UnknownKey(c) => error(column, format!("can not type `{}`", c)),
+                    err => err
+                })?;
+                for ev in typed.evs {
+                    keyboard.evs.push(KeyboardEv { timestamp: now + ev.timestamp, ..ev });
+                }
+                now += typed.till;
+            }
+            "click" | "down" | "up" => {
+                let (button, position) = match rest.split_once(" at ") {
+                    Some((button, position)) => (button.trim(), position),
+                    None => return Err(error(column, String::from("expect `<button> at <x>,<y>`")))
+                };
+                let position = match position.split_once(',') {
+                    Some((x, y)) => match (x.trim().parse(), y.trim().parse()) {
+                        (Ok(x), Ok(y)) => (x, y),
+                        _ => return Err(error(column + rest.len() - position.len(), format!("invalid position `{}`", position)))
+                    },
+                    None => return Err(error(column + rest.len() - position.len(), format!("invalid position `{}`", position)))
+                };
+                let down = parse_button(button, true).ok_or_else(|| error(column, format!("unknown button `{}`", button)))?;
+                let up = parse_button(button, false).unwrap_or(down);
+
+                if command != "up" {
+                    mouse.evs.push(MouseEv { ev_name: down, position, timestamp: now });
+                }
+                match command {
+                    "down" => buttons_held.push((down, position)),
+                    _ => {
+                        mouse.evs.push(MouseEv { ev_name: up, position, timestamp: now });
+                        buttons_held.retain(|(held, _)| button_name(*held) != button);
+                    }
+                }
+            }
+            command => return Err(error(indent + 1, format!("unknown command `{}`", command)))
+        }
+    }
+
+    keyboard.till = now;
+    mouse.till = now;
+    Ok((keyboard, mouse))
+}
+
+/// `"..."` with the escapes `\"`, `\\`, `\n` and `\t`
+fn parse_string(source: &str) -> Option<String> {
+    let inner = source.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                c @ ('"' | '\\') => text.push(c),
+                _ => return None
+            },
+            '"' => return None,
+            c => text.push(c)
+        }
+    }
+    Some(text)
+}
+// endregion
+
+// region printer
+enum Step {
+    Key { key: Keycode, code: &'static str, press: bool },
+    Tap { key: Keycode, code: &'static str, hold: u64 },
+    /// text typed by `type` in `duration` ms
+    Type { text: String, duration: u64 },
+    Mouse(MouseEv),
+    Click(MouseEv),
+}
+
+/// `"..."` which `parse_string` reads back
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Print the actions in the macro language (see `parse_dsl`).
+/// A press and the release of the same key right after it are printed as `tap` (with `hold` if they are apart), a
+/// press and a release of a button at the same time and position as `click`. Taps of printable keys typed with the
+/// timings of `type` (`TypingProfile::default()`) while no key is held are printed as `type`, so the script is read
+/// back to the same events. Keys without `code` in frontend are left out.
+pub(crate) fn render_dsl(keyboard: Option<&KeyboardAction>, mouse: Option<&MouseAction>) -> String {
+    let mut steps: Vec<(u64, Step)> = vec![];
+    let mut till = 0;
+
+    if let Some(keyboard) = keyboard {
+        for ev in keyboard.evs.iter() {
+            if let Some(code) = KeyboardMapper::dq_to_front(ev.code) {
+                steps.push((ev.timestamp, Step::Key { key: ev.code, code, press: ev.press }));
+            }
+        }
+        till = till.max(keyboard.till);
+    }
+    if let Some(mouse) = mouse {
+        for ev in mouse.evs.iter() {
+            steps.push((ev.timestamp, Step::Mouse(*ev)));
+        }
+        till = till.max(mouse.till);
+    }
+    // stable, the keyboard goes first at the same time
+    steps.sort_by_key(|(timestamp, _)| *timestamp);
+
+    // merge the taps and the clicks
+    let mut merged: Vec<(u64, Step)> = vec![];
+    for (timestamp, step) in steps {
+        let merged_step = match (&step, merged.last()) {
+            (Step::Key { key, code, press: false }, Some((pressed_at, Step::Key { key: last_key, press: true, .. }))) if key == last_key => {
+                Some((*pressed_at, Step::Tap { key: *key, code, hold: timestamp - *pressed_at }))
+            }
+            (Step::Mouse(up), Some((down_at, Step::Mouse(down))))
+            if *down_at == timestamp
+                && up.position == down.position
+                && MouseMapper::ev_name_to_dq(up.ev_name) == (MouseMapper::ev_name_to_dq(down.ev_name).0, false)
+                && MouseMapper::ev_name_to_dq(down.ev_name).1 => {
+                Some((timestamp, Step::Click(*down)))
+            }
+            _ => None
+        };
+        match merged_step {
+            Some(merged_step) => {
+                merged.pop();
+                merged.push(merged_step);
+            }
+            None => merged.push((timestamp, step))
+        }
+    }
+
+    // merge the runs of taps `type` would type
+    let mut typed: Vec<(u64, Step)> = vec![];
+    // the taps of the run with their characters
+    let mut run: Vec<(u64, Step, char)> = vec![];
+    let mut held: HashSet<Keycode> = HashSet::new();
+    fn flush(run: &mut Vec<(u64, Step, char)>, typed: &mut Vec<(u64, Step)>) {
+        if run.len() > 1 {
+            let start = run[0].0;
+            let text = run.iter().map(|(_, _, c)| *c).collect();
+            let duration = run[run.len() - 1].0 + DEFAULT_DWELL - start;
+            typed.push((start, Step::Type { text, duration }));
+            run.clear();
+        }
+        typed.extend(run.drain(..).map(|(timestamp, step, _)| (timestamp, step)));
+    }
+    for (timestamp, step) in merged {
+        let c = match step {
+            Step::Tap { key, hold: DEFAULT_DWELL, .. } if held.is_empty() => typed_char(key),
+            _ => None
+        };
+        match c {
+            Some(c) => {
+                if let Some((last_at, _, _)) = run.last() {
+                    if timestamp != *last_at + DEFAULT_DWELL + DEFAULT_FLIGHT {
+                        flush(&mut run, &mut typed);
+                    }
+                }
+                run.push((timestamp, step, c));
+            }
+            None => {
+                flush(&mut run, &mut typed);
+                if let Step::Key { key, press, .. } = step {
+                    match press {
+                        true => held.insert(key),
+                        false => held.remove(&key)
+                    };
+                }
+                typed.push((timestamp, step));
+            }
+        }
+    }
+    flush(&mut run, &mut typed);
+
+    let mut script = String::new();
+    let mut now = 0;
+    for (timestamp, step) in typed {
+        if timestamp > now {
+            script.push_str(&format!("wait {}ms\n", timestamp - now));
+            now = timestamp;
+        }
+        script.push_str(&match step {
+            Step::Key { code, press: true, .. } => format!("press {}\n", code),
+            Step::Key { code, press: false, .. } => format!("release {}\n", code),
+            Step::Tap { code, hold: 0, .. } => format!("tap {}\n", code),
+            Step::Tap { code, hold, .. } => {
+                now += hold;
+                format!("tap {} hold {}ms\n", code, hold)
+            }
+            Step::Type { text, duration } => {
+                now += duration;
+                format!("type {}\n", quote(&text))
+            }
+            Step::Mouse(ev) => {
+                let command = if MouseMapper::ev_name_to_dq(ev.ev_name).1 { "down" } else { "up" };
+                format!("{} {} at {},{}\n", command, button_name(ev.ev_name), ev.position.0, ev.position.1)
+            }
+            Step::Click(ev) => format!("click {} at {},{}\n", button_name(ev.ev_name), ev.position.0, ev.position.1),
+        });
+    }
+    if till > now {
+        script.push_str(&format!("wait {}ms\n", till - now));
+    }
+    script
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    /// the action in the macro language (see `parse_dsl`)
+    pub fn to_dsl(&self) -> String {
+        render_dsl(Some(self), None)
+    }
+
+    /// parse the macro language (see `parse_dsl`), the mouse commands are left out.
+    pub fn from_dsl(script: &str) -> Result<KeyboardAction, Error> {
+        Ok(parse_dsl(script)?.0)
+    }
+}
+
+impl MouseAction {
+    /// the action in the macro language (see `parse_dsl`)
+    pub fn to_dsl(&self) -> String {
+        render_dsl(None, Some(self))
+    }
+
+    /// parse the macro language (see `parse_dsl`), the keyboard commands are left out.
+    pub fn from_dsl(script: &str) -> Result<MouseAction, Error> {
+        Ok(parse_dsl(script)?.1)
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let script = "\
+            # log in\n\
+            press LShift\n\
+            tap KeyA\n\
+            release Shift\n\
+            wait 1.5s\n\
+            type \"hi\"\n\
+            down left at 10,20\n\
+            press ControlLeft\n\
+            wait 200ms\n\
+            release all\n";
+        let (keyboard, mouse) = parse_dsl(script).unwrap();
+
+        let codes: Vec<(Keycode, bool, u64)> = keyboard.evs.iter().map(|ev| (ev.code, ev.press, ev.timestamp)).collect();
+        assert_eq!(&codes[..4], &[
+            (Keycode::LShift, true, 0),
+            (Keycode::A, true, 0),
+            (Keycode::A, false, 0),
+            (Keycode::LShift, false, 0),
+        ]);
+        assert_eq!(keyboard.to_text().text, "Ahi");
+        // `hi` is typed in 80 + 120 + 80 ms
+        assert_eq!(*codes.last().unwrap(), (Keycode::LControl, false, 1500 + 280 + 200));
+        assert_eq!(mouse.evs.len(), 2);
+        assert!(matches!(mouse.evs[1].ev_name, MouseEventName::LeftUp));
+        assert_eq!(mouse.evs[1].position, (10, 20));
+        assert_eq!(keyboard.till, 1980);
+    }
+
+    #[test]
+    fn print_and_parse() {
+        let keyboard = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::LShift, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 150 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 150 },
+                KeyboardEv { code: Keycode::LShift, press: false, timestamp: 300 },
+            ],
+            till: 400,
+        };
+        let mouse = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::RightDown, position: (5, 6), timestamp: 300 },
+                MouseEv { ev_name: MouseEventName::RightUp, position: (5, 6), timestamp: 300 },
+            ],
+            till: 400,
+        };
+        let script = render_dsl(Some(&keyboard), Some(&mouse));
+        assert_eq!(script, "wait 100ms\npress ShiftLeft\nwait 50ms\ntap KeyA\nwait 150ms\nrelease ShiftLeft\nclick right at 5,6\nwait 100ms\n");
+
+        let (keyboard_again, mouse_again) = parse_dsl(&script).unwrap();
+        assert_eq!(render_dsl(Some(&keyboard_again), Some(&mouse_again)), script);
+        assert_eq!(keyboard_again.till, 400);
+    }
+
+    /// a held key is a `tap` with `hold`, taps typed as `type` types are merged back into `type`
+    #[test]
+    fn print_taps_and_text() {
+        let mut keyboard = KeyboardAction::from_text("a b\\c\n", TypingProfile::default()).unwrap();
+        let typed_till = keyboard.till;
+        keyboard.evs.push(KeyboardEv { code: Keycode::LControl, press: true, timestamp: typed_till + 500 });
+        keyboard.evs.push(KeyboardEv { code: Keycode::S, press: true, timestamp: typed_till + 500 });
+        keyboard.evs.push(KeyboardEv { code: Keycode::S, press: false, timestamp: typed_till + 580 });
+        keyboard.evs.push(KeyboardEv { code: Keycode::LControl, press: false, timestamp: typed_till + 600 });
+        keyboard.till = typed_till + 600;
+
+        let script = keyboard.to_dsl();
+        assert_eq!(script, "type \"a b\\\\c\\n\"\nwait 500ms\npress ControlLeft\ntap KeyS hold 80ms\nwait 20ms\nrelease ControlLeft\n");
+
+        let again = KeyboardAction::from_dsl(&script).unwrap();
+        let events = |action: &KeyboardAction| action.evs.iter().map(|ev| (ev.code, ev.press, ev.timestamp)).collect::<Vec<_>>();
+        assert_eq!(events(&again), events(&keyboard));
+        assert_eq!(again.till, keyboard.till);
+    }
+
+    /// errors tell the line and the column
+    #[test]
+    fn parse_errors() {
+        match parse_dsl("wait 10ms\n  tap Hyper") {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((line, column), (2, 7)),
+            _ => panic!("expect a parse error"),
+        }
+        match parse_dsl("click left at 1;2") {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((line, column), (1, 15)),
+            _ => panic!("expect a parse error"),
+        }
+        assert!(matches!(parse_dsl("wait 10"), Err(Error::Parse { line: 1, column: 6, .. })));
+        assert!(matches!(parse_dsl("tap KeyA hold 1m"), Err(Error::Parse { line: 1, column: 15, .. })));
+        assert!(matches!(parse_dsl("jump"), Err(Error::Parse { line: 1, column: 1, .. })));
+        assert!(matches!(parse_dsl("type \"日本\""), Err(Error::Parse { line: 1, column: 6, .. })));
+    }
+}
+// endregion
@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use crate::{KeyboardAction, MouseAction};
+use crate::timeline::TimedEvent;
+
+// region edit
+/// `till` is not before the last event
+fn fit_till<E: TimedEvent>(evs: &[E], till: u64) -> u64 {
+    evs.iter().map(|ev| ev.timestamp()).max().unwrap_or(0).max(till)
+}
+
+fn trim_events<E: TimedEvent>(evs: &mut Vec<E>, till: &mut u64, from: u64, to: u64) {
+    evs.retain(|ev| ev.timestamp() >= from && ev.timestamp() <= to);
+    for ev in evs.iter_mut() {
+        ev.set_timestamp(ev.timestamp() - from);
+    }
+    *till = fit_till(evs, (*till).min(to).saturating_sub(from));
+}
+
+fn cut_events<E: TimedEvent>(evs: &mut Vec<E>, till: &mut u64, from: u64, to: u64) {
+    let length = to.saturating_sub(from);
+    evs.retain(|ev| ev.timestamp() < from || ev.timestamp() >= to);
+    for ev in evs.iter_mut() {
+        if ev.timestamp() >= to {
+            ev.set_timestamp(ev.timestamp() - length);
+        }
+    }
+    // the part of the cut before `till`
+    let cut = (*till).min(to).saturating_sub(from);
+    *till = fit_till(evs, *till - cut);
+}
+
+fn insert_events<E: TimedEvent>(evs: &mut Vec<E>, till: &mut u64, other: &[E], other_till: u64, at: u64) {
+    let (before, after): (Vec<E>, Vec<E>) = evs.drain(..).partition(|ev| ev.timestamp() < at);
+    *evs = before;
+    for ev in other.iter() {
+        let mut ev = *ev;
+        ev.set_timestamp(ev.timestamp() + at);
+        evs.push(ev);
+    }
+    for mut ev in after {
+        ev.set_timestamp(ev.timestamp() + other_till);
+        evs.push(ev);
+    }
+    *till = fit_till(evs, match *till >= at {
+        true => *till + other_till,
+        false => at + other_till
+    });
+}
+
+fn shift_events<E: TimedEvent>(evs: &mut [E], till: &mut u64, offset: i64) {
+    let shift = |timestamp: u64| (timestamp as i64 + offset).max(0) as u64;
+    for ev in evs.iter_mut() {
+        ev.set_timestamp(shift(ev.timestamp()));
+    }
+    *till = fit_till(evs, shift(*till));
+}
+
+/// how `compress_gaps` shortens the idle gaps
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GapPolicy {
+    /// no gap is longer than this (ms)
+    Cap(u64),
+    /// the part of a gap over `threshold` ms is multiplied by `factor`
+    Scale { threshold: u64, factor: f64 },
+}
+
+impl GapPolicy {
+    fn apply(&self, gap: u64) -> u64 {
+        match *self {
+            GapPolicy::Cap(max) => gap.min(max),
+            GapPolicy::Scale { threshold, factor } => match gap > threshold && factor >= 0.0 && factor.is_finite() {
+                true => threshold + ((gap - threshold) as f64 * factor).round() as u64,
+                false => gap
+            }
+        }
+    }
+}
+
+fn compress_gap_events<E: TimedEvent>(evs: &mut [E], till: &mut u64, policy: GapPolicy) {
+    let mut held: HashSet<E::Key> = HashSet::new();
+    // timestamps before and after the compression of the last event
+    let mut last: Option<(u64, u64)> = None;
+
+    for ev in evs.iter_mut() {
+        let timestamp = match last {
+            // the leading silence is left out
+            None => 0,
+            Some((before, after)) => {
+                let gap = ev.timestamp().saturating_sub(before);
+                // inside a chord or a drag the gap is kept as it is
+                match held.is_empty() {
+                    true => after + policy.apply(gap),
+                    false => after + gap
+                }
+            }
+        };
+        last = Some((ev.timestamp(), timestamp));
+        ev.set_timestamp(timestamp);
+
+        match ev.key() {
+            (key, true) => held.insert(key),
+            (key, false) => held.remove(&key),
+        };
+    }
+
+    // the trailing silence is left out
+    *till = last.map(|(_, after)| after).unwrap_or(0);
+}
+
+fn scale_events<E: TimedEvent>(evs: &mut [E], till: &mut u64, factor: f64) {
+    if !(factor > 0.0 && factor.is_finite()) {
+        return;
+    }
+    let scale = |timestamp: u64| (timestamp as f64 * factor).round() as u64;
+    for ev in evs.iter_mut() {
+        ev.set_timestamp(scale(ev.timestamp()));
+    }
+    *till = fit_till(evs, scale(*till));
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    /// Keep the events in `from..=to` only, rebased so that `from` is 0.
+    /// Keys pressed before `from` or released after `to` are left unpaired, see `normalize`.
+    pub fn trim(&mut self, from: u64, to: u64) {
+        trim_events(&mut self.evs, &mut self.till, from, to);
+    }
+
+    /// Remove the events in `from..to` and close the gap, the events after move `to - from` earlier.
+    pub fn cut(&mut self, from: u64, to: u64) {
+        cut_events(&mut self.evs, &mut self.till, from, to);
+    }
+
+    /// Append `other` after `gap` ms from `till`.
+    pub fn concat(&mut self, other: &KeyboardAction, gap: u64) {
+        let at = self.till + gap;
+        insert_events(&mut self.evs, &mut self.till, &other.evs, other.till, at);
+    }
+
+    /// Insert `other` at `at`, the events from `at` on move `other.till` later.
+    pub fn insert(&mut self, other: &KeyboardAction, at: u64) {
+        insert_events(&mut self.evs, &mut self.till, &other.evs, other.till, at);
+    }
+
+    /// Move every event (and `till`) by `offset` ms, timestamps stop at 0.
+    pub fn shift(&mut self, offset: i64) {
+        shift_events(&mut self.evs, &mut self.till, offset);
+    }
+
+    /// Multiply every timestamp (and `till`) by `factor`, e.g. `0.5` plays twice as fast.
+    /// A `factor` which is not positive is ignored.
+    pub fn scale(&mut self, factor: f64) {
+        scale_events(&mut self.evs, &mut self.till, factor);
+    }
+
+    /// Shorten the idle gaps between events by `policy`, and leave out the silence before the first event and after
+    /// the last one. The gaps while a key is held (e.g. inside `Ctrl+C`) are kept, so are the held keys.
+    /// The events should be in order, see `normalize`.
+    pub fn compress_gaps(&mut self, policy: GapPolicy) {
+        compress_gap_events(&mut self.evs, &mut self.till, policy);
+    }
+}
+
+impl MouseAction {
+    /// see `KeyboardAction.trim`
+    pub fn trim(&mut self, from: u64, to: u64) {
+        trim_events(&mut self.evs, &mut self.till, from, to);
+    }
+
+    /// see `KeyboardAction.cut`
+    pub fn cut(&mut self, from: u64, to: u64) {
+        cut_events(&mut self.evs, &mut self.till, from, to);
+    }
+
+    /// see `KeyboardAction.concat`
+    pub fn concat(&mut self, other: &MouseAction, gap: u64) {
+        let at = self.till + gap;
+        insert_events(&mut self.evs, &mut self.till, &other.evs, other.till, at);
+    }
+
+    /// see `KeyboardAction.insert`
+    pub fn insert(&mut self, other: &MouseAction, at: u64) {
+        insert_events(&mut self.evs, &mut self.till, &other.evs, other.till, at);
+    }
+
+    /// see `KeyboardAction.shift`
+    pub fn shift(&mut self, offset: i64) {
+        shift_events(&mut self.evs, &mut self.till, offset);
+    }
+
+    /// see `KeyboardAction.scale`
+    pub fn scale(&mut self, factor: f64) {
+        scale_events(&mut self.evs, &mut self.till, factor);
+    }
+
+    /// see `KeyboardAction.compress_gaps`, the gaps while a button is held (e.g. in a drag) are kept.
+    pub fn compress_gaps(&mut self, policy: GapPolicy) {
+        compress_gap_events(&mut self.evs, &mut self.till, policy);
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use device_query::Keycode;
+    use crate::{KeyboardEv, MouseEv, MouseEventName};
+    use super::*;
+
+    /// A at 100-200, B at 300-400, till 500
+    fn action() -> KeyboardAction {
+        KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 200 },
+                KeyboardEv { code: Keycode::B, press: true, timestamp: 300 },
+                KeyboardEv { code: Keycode::B, press: false, timestamp: 400 },
+            ],
+            till: 500,
+        }
+    }
+
+    fn timeline(action: &KeyboardAction) -> Vec<(Keycode, u64)> {
+        action.evs.iter().map(|ev| (ev.code, ev.timestamp)).collect()
+    }
+
+    #[test]
+    fn trim_and_cut() {
+        let mut trimmed = action();
+        trimmed.trim(150, 350);
+        assert_eq!(timeline(&trimmed), vec![(Keycode::A, 50), (Keycode::B, 150)]);
+        assert_eq!(trimmed.till, 200);
+
+        let mut cut = action();
+        cut.cut(150, 350);
+        assert_eq!(timeline(&cut), vec![(Keycode::A, 100), (Keycode::B, 200)]);
+        assert_eq!(cut.till, 300);
+    }
+
+    #[test]
+    fn concat_and_insert() {
+        let mut concat = action();
+        concat.concat(&action(), 100);
+        assert_eq!(concat.evs.len(), 8);
+        assert_eq!(concat.evs[4].timestamp, 700);
+        assert_eq!(concat.till, 1100);
+
+        let mut inserted = action();
+        inserted.insert(&action(), 250);
+        assert_eq!(timeline(&inserted), vec![
+            (Keycode::A, 100),
+            (Keycode::A, 200),
+            (Keycode::A, 350),
+            (Keycode::A, 450),
+            (Keycode::B, 550),
+            (Keycode::B, 650),
+            (Keycode::B, 800),
+            (Keycode::B, 900),
+        ]);
+        assert_eq!(inserted.till, 1000);
+    }
+
+    #[test]
+    fn shift_and_scale() {
+        let mut shifted = action();
+        shifted.shift(-150);
+        assert_eq!(timeline(&shifted)[..2], [(Keycode::A, 0), (Keycode::A, 50)]);
+        assert_eq!(shifted.till, 350);
+
+        let mut mouse = MouseAction {
+            evs: vec![MouseEv { ev_name: MouseEventName::LeftDown, position: (0, 0), timestamp: 300 }],
+            till: 400,
+        };
+        mouse.scale(0.5);
+        assert_eq!(mouse.evs[0].timestamp, 150);
+        assert_eq!(mouse.till, 200);
+        mouse.scale(-1.0);
+        assert_eq!(mouse.till, 200);
+    }
+
+    /// the gaps inside a chord are kept, the others are capped
+    #[test]
+    fn compress_gaps() {
+        let mut chord = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::LControl, press: true, timestamp: 5000 },
+                KeyboardEv { code: Keycode::C, press: true, timestamp: 8000 },
+                KeyboardEv { code: Keycode::C, press: false, timestamp: 8100 },
+                KeyboardEv { code: Keycode::LControl, press: false, timestamp: 9000 },
+                KeyboardEv { code: Keycode::V, press: true, timestamp: 20000 },
+                KeyboardEv { code: Keycode::V, press: false, timestamp: 20100 },
+            ],
+            till: 60000,
+        };
+        chord.compress_gaps(GapPolicy::Cap(500));
+        let timestamps: Vec<u64> = chord.evs.iter().map(|ev| ev.timestamp).collect();
+        assert_eq!(timestamps, vec![0, 3000, 3100, 4000, 4500, 4600]);
+        assert_eq!(chord.till, 4600);
+
+        let mut drag = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (0, 0), timestamp: 1000 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (9, 9), timestamp: 4000 },
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (9, 9), timestamp: 10000 },
+            ],
+            till: 10000,
+        };
+        drag.compress_gaps(GapPolicy::Scale { threshold: 1000, factor: 0.1 });
+        let timestamps: Vec<u64> = drag.evs.iter().map(|ev| ev.timestamp).collect();
+        assert_eq!(timestamps, vec![0, 3000, 4500]);
+    }
+}
+// endregion
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::io;
+use crate::Diagnostic;
+
+// region error
+/// errors of toca
+#[derive(Debug)]
+pub enum Error {
+    /// the source can not be parsed, `line` and `column` start from 1 (0 if unknown)
+    Parse { line: usize, column: usize, message: String },
+    /// a key code without mapping
+    UnknownKey(String),
+    /// a mouse event name out of 1-6
+    UnknownMouseEvent(usize),
+    /// the event at `index` (from 0) is invalid
+    Event { index: usize, cause: Box<Error> },
+    /// the player is playing
+    Busy,
+    /// the action is refused by a strict player, with the `Severity::Error` diagnostics
+    Invalid(Vec<Diagnostic>),
+    /// the record/simulate backend failed
+    Backend(String),
+    /// a file extension no format is known for
+    UnknownFormat(String),
+    /// reading or writing failed
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse { line, column, message } => {
+                write!(f, "parse error at line {} column {}: {}", line, column, message)
+            }
+            Error::UnknownKey(code) => write!(f, "unknown key `{}`", code),
+            Error::UnknownMouseEvent(ev_name) => write!(f, "unknown mouse event `{}`", ev_name),
+            Error::Event { index, cause } => write!(f, "event #{}: {}", index, cause),
+            Error::Busy => write!(f, "the player is playing"),
+            Error::Invalid(diagnostics) => match diagnostics.first() {
+                Some(first) if diagnostics.len() > 1 => write!(f, "invalid action, {} and {} more", first, diagnostics.len() - 1),
+                Some(first) => write!(f, "invalid action, {}", first),
+                None => write!(f, "invalid action"),
+            },
+            Error::Backend(message) => write!(f, "backend error: {}", message),
+            Error::UnknownFormat(ext) => write!(f, "unknown format `{}`", ext),
+            Error::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Event { cause, .. } => Some(cause.as_ref()),
+            _ => None
+        }
+    }
+}
+
+/// an event left out when a serialized action is parsed leniently
+#[derive(Debug)]
+pub struct SkippedEvent {
+    /// index (from 0) of the event in the source
+    pub index: usize,
+    /// why it is left out, e.g. `Error::UnknownKey`
+    pub reason: Error,
+}
+
+impl SkippedEvent {
+    /// the error a strict parse fails with
+    pub fn into_error(self) -> Error {
+        Error::Event {
+            index: self.index,
+            cause: Box::new(self.reason),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            return Error::Io(err.into());
+        }
+        Error::Parse {
+            line: err.line(),
+            column: err.column(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        let (line, column) = match err.line_col() {
+            Some((line, column)) => (line + 1, column + 1),
+            None => (0, 0)
+        };
+        Error::Parse {
+            line,
+            column,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Error::Parse {
+            line: 0,
+            column: 0,
+            message: err.to_string(),
+        }
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{KeyboardAction, KeyMap};
+
+    #[test]
+    fn error_from_serde() {
+        match KeyboardAction::from_string("{\"evs\":[],\n\"till\":}") {
+            Err(Error::Parse { line, column, .. }) => {
+                assert_eq!(line, 2);
+                assert_eq!(column, 8);
+            }
+            _ => panic!("expect a parse error"),
+        }
+    }
+
+    #[test]
+    fn error_from_toml() {
+        match KeyMap::from_toml("[remap]\nCapsLock = ") {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("expect a parse error"),
+        }
+        match KeyMap::from_toml("[remap]\nCapsLock = \"Nothing\"") {
+            Err(Error::UnknownKey(code)) => assert_eq!(code, "Nothing"),
+            _ => panic!("expect an unknown key"),
+        }
+    }
+
+    #[test]
+    fn error_display() {
+        assert_eq!(Error::UnknownMouseEvent(7).to_string(), "unknown mouse event `7`");
+        assert_eq!(Error::Busy.to_string(), "the player is playing");
+        assert_eq!(Error::Backend(String::from("no display")).to_string(), "backend error: no display");
+        assert_eq!(SkippedEvent { index: 3, reason: Error::UnknownKey(String::from("Foo")) }.into_error().to_string(), "event #3: unknown key `Foo`");
+    }
+}
+// endregion
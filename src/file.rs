@@ -0,0 +1,294 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use serde::Serialize;
+use crate::{CombinedAction, Document, Error, KeyboardAction, KeyMap, Metadata, MouseAction};
+
+// region format
+/// formats to save an action in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// the document of `to_string`, extension `.json`
+    Json,
+    /// the same document, indented so that files diff nicely
+    PrettyJson,
+    /// the compact form of `BinaryWriter`, extension `.toca`
+    Binary,
+    /// one event per line as `JournalWriter` writes while recording, extension `.jsonl`
+    JsonLines,
+    /// one event per row for spreadsheets (see `CSV_HEADER`), extension `.csv`, the metadata is not kept
+    Csv,
+}
+
+impl Format {
+    /// the format of a path by its extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Format, Error> {
+        let extension = path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Ok(Format::Json),
+            Some("toca") => Ok(Format::Binary),
+            Some("jsonl") => Ok(Format::JsonLines),
+            Some("csv") => Ok(Format::Csv),
+            Some(ext) => Err(Error::UnknownFormat(String::from(ext))),
+            None => Err(Error::UnknownFormat(String::new()))
+        }
+    }
+}
+
+fn write_document<T, W>(writer: W, document: &Document<T>, pretty: bool) -> Result<(), Error>
+    where T: Serialize, W: Write {
+    let mut writer = BufWriter::new(writer);
+    match pretty {
+        true => serde_json::to_writer_pretty(&mut writer, document)?,
+        false => serde_json::to_writer(&mut writer, document)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_string<R: Read>(reader: R) -> Result<String, Error> {
+    let mut string_source = String::new();
+    BufReader::new(reader).read_to_string(&mut string_source)?;
+    Ok(string_source)
+}
+// endregion
+
+// region keyboard action
+impl KeyboardAction {
+    /// save to `path`, the format is picked by the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let format = Format::from_path(&path)?;
+        self.save_as(path, format)
+    }
+
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<(), Error> {
+        self.write_to(File::create(path)?, format)
+    }
+
+    /// load from `path`, the format is picked by the extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<KeyboardAction, Error> {
+        let format = Format::from_path(&path)?;
+        KeyboardAction::load_as(path, format)
+    }
+
+    pub fn load_as<P: AsRef<Path>>(path: P, format: Format) -> Result<KeyboardAction, Error> {
+        KeyboardAction::read_from(File::open(path)?, format)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W, format: Format) -> Result<(), Error> {
+        self.write_to_with_meta(writer, format, &Metadata::of_host())
+    }
+
+    /// `write_to` with the given metadata in the header.
+    pub fn write_to_with_meta<W: Write>(&self, writer: W, format: Format, meta: &Metadata) -> Result<(), Error> {
+        match format {
+            Format::Json | Format::PrettyJson => {
+                let document = Document::new("keyboard", meta.clone(), self.to_serializable_strict(&KeyMap::new())?);
+                write_document(writer, &document, format == Format::PrettyJson)
+            }
+            Format::Binary => self.write_binary(BufWriter::new(writer), meta),
+            Format::JsonLines => self.write_journal(BufWriter::new(writer), meta),
+            Format::Csv => self.write_csv(BufWriter::new(writer)),
+        }
+    }
+
+    pub fn read_from<R: Read>(reader: R, format: Format) -> Result<KeyboardAction, Error> {
+        match format {
+            Format::Json | Format::PrettyJson => KeyboardAction::from_string(&read_string(reader)?),
+            Format::Binary => Ok(KeyboardAction::read_binary(BufReader::new(reader))?.0),
+            Format::JsonLines => Ok(KeyboardAction::read_journal(BufReader::new(reader))?.0),
+            Format::Csv => KeyboardAction::read_csv(BufReader::new(reader)),
+        }
+    }
+}
+// endregion
+
+// region mouse action
+impl MouseAction {
+    /// save to `path`, the format is picked by the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let format = Format::from_path(&path)?;
+        self.save_as(path, format)
+    }
+
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<(), Error> {
+        self.write_to(File::create(path)?, format)
+    }
+
+    /// load from `path`, the format is picked by the extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<MouseAction, Error> {
+        let format = Format::from_path(&path)?;
+        MouseAction::load_as(path, format)
+    }
+
+    pub fn load_as<P: AsRef<Path>>(path: P, format: Format) -> Result<MouseAction, Error> {
+        MouseAction::read_from(File::open(path)?, format)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W, format: Format) -> Result<(), Error> {
+        self.write_to_with_meta(writer, format, &Metadata::of_host())
+    }
+
+    /// `write_to` with the given metadata in the header.
+    pub fn write_to_with_meta<W: Write>(&self, writer: W, format: Format, meta: &Metadata) -> Result<(), Error> {
+        match format {
+            Format::Json | Format::PrettyJson => {
+                let document = Document::new("mouse", meta.clone(), self.to_serializable());
+                write_document(writer, &document, format == Format::PrettyJson)
+            }
+            Format::Binary => self.write_binary(BufWriter::new(writer), meta),
+            Format::JsonLines => self.write_journal(BufWriter::new(writer), meta),
+            Format::Csv => self.write_csv(BufWriter::new(writer)),
+        }
+    }
+
+    pub fn read_from<R: Read>(reader: R, format: Format) -> Result<MouseAction, Error> {
+        match format {
+            Format::Json | Format::PrettyJson => MouseAction::from_string(&read_string(reader)?),
+            Format::Binary => Ok(MouseAction::read_binary(BufReader::new(reader))?.0),
+            Format::JsonLines => Ok(MouseAction::read_journal(BufReader::new(reader))?.0),
+            Format::Csv => MouseAction::read_csv(BufReader::new(reader)),
+        }
+    }
+}
+// endregion
+
+// region combined action
+impl CombinedAction {
+    /// save to `path`, the format is picked by the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let format = Format::from_path(&path)?;
+        self.save_as(path, format)
+    }
+
+    pub fn save_as<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<(), Error> {
+        self.write_to(File::create(path)?, format)
+    }
+
+    /// load from `path`, the format is picked by the extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<CombinedAction, Error> {
+        let format = Format::from_path(&path)?;
+        CombinedAction::load_as(path, format)
+    }
+
+    pub fn load_as<P: AsRef<Path>>(path: P, format: Format) -> Result<CombinedAction, Error> {
+        CombinedAction::read_from(File::open(path)?, format)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W, format: Format) -> Result<(), Error> {
+        self.write_to_with_meta(writer, format, &Metadata::of_host())
+    }
+
+    /// `write_to` with the given metadata in the header.
+    pub fn write_to_with_meta<W: Write>(&self, writer: W, format: Format, meta: &Metadata) -> Result<(), Error> {
+        match format {
+            Format::Json | Format::PrettyJson => {
+                let document = Document::new("combined", meta.clone(), self.to_serializable_strict()?);
+                write_document(writer, &document, format == Format::PrettyJson)
+            }
+            Format::Binary => self.write_binary(BufWriter::new(writer), meta),
+            Format::JsonLines => self.write_journal(BufWriter::new(writer), meta),
+            Format::Csv => self.write_csv(BufWriter::new(writer)),
+        }
+    }
+
+    pub fn read_from<R: Read>(reader: R, format: Format) -> Result<CombinedAction, Error> {
+        match format {
+            Format::Json | Format::PrettyJson => CombinedAction::from_string(&read_string(reader)?),
+            Format::Binary => Ok(CombinedAction::read_binary(BufReader::new(reader))?.0),
+            Format::JsonLines => Ok(CombinedAction::read_journal(BufReader::new(reader))?.0),
+            Format::Csv => CombinedAction::read_csv(BufReader::new(reader)),
+        }
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+    use device_query::Keycode;
+    use crate::{KeyboardEv, MouseEv, MouseEventName};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("toca-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(Format::from_path("a/b/macro.json").unwrap(), Format::Json);
+        assert_eq!(Format::from_path("MACRO.JSON").unwrap(), Format::Json);
+        assert_eq!(Format::from_path("macro.toca").unwrap(), Format::Binary);
+        assert_eq!(Format::from_path("macro.jsonl").unwrap(), Format::JsonLines);
+        assert_eq!(Format::from_path("macro.csv").unwrap(), Format::Csv);
+        assert!(matches!(Format::from_path("macro.txt"), Err(Error::UnknownFormat(ext)) if ext == "txt"));
+        assert!(matches!(Format::from_path("macro"), Err(Error::UnknownFormat(_))));
+    }
+
+    #[test]
+    fn keyboard_save_load() {
+        let action = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 10 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 90 },
+            ],
+            till: 100,
+        };
+
+        let path = temp_path("keyboard.json");
+        action.save(&path).unwrap();
+        let again = KeyboardAction::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(again.evs.len(), 2);
+        assert_eq!(again.evs[1].code, Keycode::A);
+        assert_eq!(again.till, 100);
+
+        let path = temp_path("keyboard.toca");
+        action.save(&path).unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(b"TOCA"));
+        let again = KeyboardAction::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(again.evs.len(), 2);
+        assert_eq!(again.till, 100);
+    }
+
+    /// `write_to` writes the document of `to_string`
+    #[test]
+    fn keyboard_write_as_to_string() {
+        let action = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::Insert, press: true, timestamp: 10 },
+                KeyboardEv { code: Keycode::Insert, press: false, timestamp: 90 },
+            ],
+            till: 100,
+        };
+
+        let mut buffer: Vec<u8> = vec![];
+        action.write_to(&mut buffer, Format::Json).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer), action.to_string().unwrap());
+    }
+
+    #[test]
+    fn mouse_write_read_pretty() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (1, 2), timestamp: 10 },
+            ],
+            till: 100,
+        };
+
+        let mut buffer: Vec<u8> = vec![];
+        action.write_to(&mut buffer, Format::PrettyJson).unwrap();
+        assert!(String::from_utf8_lossy(&buffer).contains("\n  \"kind\": \"mouse\""));
+
+        let again = MouseAction::read_from(buffer.as_slice(), Format::PrettyJson).unwrap();
+        assert_eq!(again.evs[0].position, (1, 2));
+        assert_eq!(again.till, 100);
+    }
+}
+// endregion
@@ -0,0 +1,238 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use device_query::{DeviceQuery, DeviceState, Keycode};
+use enigo::{Enigo, MouseControllable};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::{Error, KeyboardMapper};
+
+// region document
+/// version of the document written by `to_string`.
+/// - `1`: the bare `{ evs, till }` of v0.1
+/// - `2`: `{ format_version, kind, meta, action }`
+pub const FORMAT_VERSION: u32 = 2;
+
+/// what a record is about, stored in the header of the document
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Metadata {
+    /// milliseconds since the unix epoch (0 if unknown)
+    #[serde(default)]
+    pub created_at: u64,
+    /// version of toca which wrote the record
+    #[serde(default)]
+    pub toca_version: String,
+    /// `std::env::consts::OS` of the machine which made the record
+    #[serde(default)]
+    pub host_os: String,
+    /// `[width, height]` of the main screen
+    #[serde(default)]
+    pub screen_size: Option<[u32; 2]>,
+    /// position of the cursor when the record started
+    #[serde(default)]
+    pub cursor_start: Option<[i32; 2]>,
+    /// `code` in frontend of the key which stopped the record
+    #[serde(default)]
+    pub stop_key: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Metadata {
+    /// metadata of a record made now, on this machine
+    pub fn new() -> Metadata {
+        Metadata {
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            ..Metadata::of_host()
+        }
+    }
+
+    /// Metadata of this machine without the time of the record (`created_at` is 0),
+    /// written by `to_string` and `write_to` so that the same action is always written the same.
+    pub fn of_host() -> Metadata {
+        Metadata {
+            toca_version: String::from(env!("CARGO_PKG_VERSION")),
+            host_os: String::from(std::env::consts::OS),
+            ..Metadata::default()
+        }
+    }
+
+    /// metadata of a record starting now, used by the recorders. `created_at` is stamped here, once.
+    pub(crate) fn of_record(device_state: &DeviceState, stop_code: Keycode) -> Metadata {
+        let (x, y) = device_state.get_mouse().coords;
+        let screen_size = match Enigo::new().main_display_size() {
+            (width, height) if width > 0 && height > 0 => Some([width as u32, height as u32]),
+            _ => None
+        };
+        Metadata {
+            screen_size,
+            cursor_start: Some([x, y]),
+            stop_key: KeyboardMapper::dq_to_front(stop_code).map(String::from),
+            ..Metadata::new()
+        }
+    }
+}
+
+/// the self-describing form of an action, `T` is `KeyboardActionSerializable` or `MouseActionSerializable`
+#[derive(Deserialize, Serialize)]
+pub struct Document<T> {
+    pub format_version: u32,
+    /// `keyboard` or `mouse`
+    pub kind: String,
+    pub meta: Metadata,
+    pub action: T,
+}
+
+impl<T> Document<T> {
+    pub fn new(kind: &str, meta: Metadata, action: T) -> Document<T> {
+        Document {
+            format_version: FORMAT_VERSION,
+            kind: String::from(kind),
+            meta,
+            action,
+        }
+    }
+}
+
+/// Bring the header of a versioned document up to `FORMAT_VERSION`, `kind` is what the caller expects.
+/// Documents of version 1 are the bare action without header, `parse_document` reads them as they are, so a
+/// `format_version` of 1 (or a missing one) is refused here.
+pub fn migrate(document: Value, kind: &str) -> Result<Value, Error> {
+    let version = match document.get("format_version") {
+        None => return Err(format_error(String::from("missing `format_version`"))),
+        Some(version) => match version.as_u64() {
+            Some(version) => version,
+            None => return Err(format_error(String::from("`format_version` should be a number")))
+        }
+    };
+
+    if version < 2 {
+        return Err(format_error(format!(
+            "unsupported format version {}, version 1 is the bare `{{ evs, till }}` without `format_version`", version
+        )));
+    } else if version > FORMAT_VERSION as u64 {
+        return Err(format_error(format!("unsupported format version {}, the latest is {}", version, FORMAT_VERSION)));
+    }
+
+    match document.get("kind").and_then(Value::as_str) {
+        Some(found) if found == kind => Ok(document),
+        Some(found) => Err(format_error(format!("expect a {} record, found a {} record", kind, found))),
+        None => Err(format_error(String::from("missing `kind`")))
+    }
+}
+
+/// the fields `parse_document` checks before reading the whole document
+#[derive(Deserialize)]
+struct DocumentHeader {
+    #[serde(default)]
+    format_version: Option<Value>,
+    #[serde(default)]
+    kind: Option<Value>,
+}
+
+/// Parse a document of any version from `json`.
+/// Only the header goes through `migrate`, the document is read from the source so that the errors keep their line
+/// and column.
+pub fn parse_document<T>(string_source: &str, kind: &str) -> Result<Document<T>, Error>
+    where T: for<'de> Deserialize<'de> {
+    let header: DocumentHeader = serde_json::from_str(string_source)?;
+    match header.format_version {
+        // version 1 is the bare action
+        None => Ok(Document::new(kind, Metadata::default(), serde_json::from_str(string_source)?)),
+        Some(version) => {
+            migrate(json!({ "format_version": version, "kind": header.kind }), kind)?;
+            Ok(serde_json::from_str(string_source)?)
+        }
+    }
+}
+
+fn format_error(message: String) -> Error {
+    Error::Parse {
+        line: 0,
+        column: 0,
+        message,
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{KeyboardAction, MouseAction};
+
+    /// v0.1 payloads are read through the migration
+    #[test]
+    fn read_v1() {
+        let action = KeyboardAction::from_string("{\"evs\":[{\"code\":\"KeyA\",\"press\":true,\"timestamp\":10}],\"till\":20}").unwrap();
+        assert_eq!(action.evs.len(), 1);
+        assert_eq!(action.till, 20);
+
+        let (action, meta) = MouseAction::from_string_with_meta("{\"evs\":[{\"ev_name\":1,\"position\":[1,2],\"timestamp\":10}],\"till\":20}").unwrap();
+        assert_eq!(action.evs.len(), 1);
+        assert_eq!(meta, Metadata::default());
+    }
+
+    #[test]
+    fn read_write_v2() {
+        let action = KeyboardAction::from_string("{\"evs\":[{\"code\":\"KeyA\",\"press\":true,\"timestamp\":10}],\"till\":20}").unwrap();
+        let mut meta = Metadata::new();
+        meta.stop_key = Some(String::from("Escape"));
+        meta.tags.push(String::from("login"));
+        meta.description = String::from("log in as admin");
+
+        let s = action.to_string_with_meta(&meta).unwrap();
+        assert!(s.starts_with("{\"format_version\":2,\"kind\":\"keyboard\""));
+
+        let (again, meta_again) = KeyboardAction::from_string_with_meta(&s).unwrap();
+        assert_eq!(again.evs.len(), 1);
+        assert_eq!(again.till, 20);
+        assert_eq!(meta_again, meta);
+        assert_eq!(meta_again.host_os, std::env::consts::OS);
+    }
+
+    /// the time of the record comes from the recorder, not from the time of writing
+    #[test]
+    fn write_without_time() {
+        let action = KeyboardAction::from_string("{\"evs\":[],\"till\":20}").unwrap();
+        let s = action.to_string().unwrap();
+        assert_eq!(KeyboardAction::from_string_with_meta(&s).unwrap().1, Metadata::of_host());
+        assert_eq!(action.to_string().unwrap(), s);
+    }
+
+    #[test]
+    fn read_wrong_kind() {
+        let s = MouseAction { evs: vec![], till: 0 }.to_string().unwrap();
+        assert!(matches!(KeyboardAction::from_string(&s), Err(Error::Parse { .. })));
+    }
+
+    #[test]
+    fn read_future_version() {
+        let s = "{\"format_version\":99,\"kind\":\"keyboard\",\"meta\":{},\"action\":{\"evs\":[],\"till\":0}}";
+        assert!(matches!(KeyboardAction::from_string(s), Err(Error::Parse { .. })));
+    }
+
+    /// version 1 has no `format_version`, an explicit one is refused with a message
+    #[test]
+    fn read_explicit_v1() {
+        let s = "{\"format_version\":1,\"evs\":[],\"till\":0}";
+        match KeyboardAction::from_string(s) {
+            Err(Error::Parse { message, .. }) => assert!(message.contains("version 1 is the bare")),
+            _ => panic!("expect a parse error"),
+        }
+    }
+
+    /// a bad field is reported where it is, in both versions
+    #[test]
+    fn read_bad_field() {
+        let v2 = "{\"format_version\":2,\"kind\":\"keyboard\",\"meta\":{},\"action\":{\"evs\":[\n{\"code\":\"KeyA\",\"press\":\"yes\",\"timestamp\":10}],\"till\":20}}";
+        assert!(matches!(KeyboardAction::from_string(v2), Err(Error::Parse { line: 2, column, .. }) if column > 0));
+
+        let v1 = "{\"evs\":[{\"code\":\"KeyA\",\"press\":\"yes\",\"timestamp\":10}],\"till\":20}";
+        assert!(matches!(KeyboardAction::from_string(v1), Err(Error::Parse { line: 1, column, .. }) if column > 0));
+    }
+}
+// endregion
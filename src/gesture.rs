@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use crate::{DOUBLE_CLICK_DISTANCE, DOUBLE_CLICK_INTERVAL, MouseAction, MouseEv, MouseEventName, MouseMapper};
+
+// region gesture
+/// ms between the press and the release of a click replayed by `Gesture.to_events`
+const CLICK_HOLD: u64 = 50;
+/// ms between the presses of a double or triple click replayed by `Gesture.to_events`
+const MULTI_CLICK_GAP: u64 = 100;
+
+/// thresholds of `MouseAction.gestures`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GestureOptions {
+    /// longest time (ms) from a press to the next press of a double or triple click
+    pub multi_click_interval: u64,
+    /// farthest distance (px, on each axis) between the presses of a double or triple click
+    pub multi_click_distance: i32,
+    /// a press released farther (px, on each axis) than this is a drag
+    pub drag_distance: i32,
+    /// a press released in place after this long (ms) is a long press
+    pub long_press: u64,
+    /// moves of a drag replayed by `Gesture.to_events`
+    pub drag_steps: usize,
+}
+
+impl Default for GestureOptions {
+    fn default() -> GestureOptions {
+        GestureOptions {
+            multi_click_interval: DOUBLE_CLICK_INTERVAL,
+            multi_click_distance: DOUBLE_CLICK_DISTANCE,
+            drag_distance: DOUBLE_CLICK_DISTANCE,
+            long_press: 800,
+            drag_steps: 20,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GestureKind {
+    Click,
+    DoubleClick,
+    TripleClick,
+    /// pressed at `Gesture.position`, released at `to`
+    Drag { to: (i32, i32) },
+    LongPress,
+}
+
+/// what a press and its release (or a few quick clicks) mean, see `MouseAction.gestures`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Gesture {
+    pub kind: GestureKind,
+    /// button of device_query: 1 left, 2 right, 3 middle
+    pub button: usize,
+    /// where the (first) press is
+    pub position: (i32, i32),
+    /// timestamp of the (first) press
+    pub start: u64,
+    /// timestamp of the (last) release
+    pub end: u64,
+}
+
+/// an event of the semantic replay, a move if `ev_name` is `None`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SemanticEv {
+    pub ev_name: Option<MouseEventName>,
+    pub position: (i32, i32),
+    pub timestamp: u64,
+}
+
+impl Gesture {
+    /// The events to replay the intent of the gesture, not the recorded events: the clicks are pressed for
+    /// `CLICK_HOLD` ms and `MULTI_CLICK_GAP` ms apart from `start`, a long press is held to `end`, and a drag moves
+    /// along a smooth path of `drag_steps` moves from `start` to `end`.
+    pub fn to_events(&self, drag_steps: usize) -> Vec<SemanticEv> {
+        let (down, up) = match (MouseMapper::dq_to_ev_name(self.button, true), MouseMapper::dq_to_ev_name(self.button, false)) {
+            (Some(down), Some(up)) => (down, up),
+            _ => return vec![]
+        };
+        let ev = |ev_name: Option<MouseEventName>, position: (i32, i32), timestamp: u64| SemanticEv { ev_name, position, timestamp };
+
+        let clicks = match self.kind {
+            GestureKind::Click => 1,
+            GestureKind::DoubleClick => 2,
+            GestureKind::TripleClick => 3,
+            GestureKind::LongPress => return vec![
+                ev(Some(down), self.position, self.start),
+                ev(Some(up), self.position, self.end),
+            ],
+            GestureKind::Drag { to } => {
+                let mut evs = vec![ev(Some(down), self.position, self.start)];
+                let steps = drag_steps.max(1);
+                for step in 1..steps {
+                    let t = step as f64 / steps as f64;
+                    // smoothstep, slow at both ends
+                    let eased = t * t * (3.0 - 2.0 * t);
+                    let lerp = |from: i32, to: i32| (from as f64 + (to - from) as f64 * eased).round() as i32;
+                    let timestamp = self.start + (self.end.saturating_sub(self.start) as f64 * t).round() as u64;
+                    evs.push(ev(None, (lerp(self.position.0, to.0), lerp(self.position.1, to.1)), timestamp));
+                }
+                evs.push(ev(Some(up), to, self.end));
+                return evs;
+            }
+        };
+
+        let mut evs = vec![];
+        for click in 0..clicks {
+            let timestamp = self.start + click * MULTI_CLICK_GAP;
+            evs.push(ev(Some(down), self.position, timestamp));
+            evs.push(ev(Some(up), self.position, timestamp + CLICK_HOLD));
+        }
+        evs
+    }
+}
+
+/// see `MouseAction.gestures`
+pub(crate) fn recognize(evs: &[MouseEv], options: &GestureOptions) -> Vec<Gesture> {
+    let mut gestures: Vec<Gesture> = vec![];
+    // the pending press of each button
+    let mut pressed: HashMap<usize, (u64, (i32, i32))> = HashMap::new();
+    // the last press of the last gesture, while it may become a double or triple click
+    let mut last_click: Option<(u64, (i32, i32))> = None;
+
+    for ev in evs.iter() {
+        let (button, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+        if press {
+            pressed.insert(button, (ev.timestamp, ev.position));
+            continue;
+        }
+        let (start, position) = match pressed.remove(&button) {
+            Some(down) => down,
+            None => continue
+        };
+
+        let moved = (ev.position.0 - position.0).abs().max((ev.position.1 - position.1).abs());
+        let kind = match moved > options.drag_distance {
+            true => GestureKind::Drag { to: ev.position },
+            false if ev.timestamp.saturating_sub(start) >= options.long_press => GestureKind::LongPress,
+            false => GestureKind::Click,
+        };
+
+        if kind == GestureKind::Click {
+            let previous = gestures.last_mut().filter(|previous| previous.button == button);
+            if let (Some(previous), Some((last_start, last_position))) = (previous, last_click) {
+                let near = (position.0 - last_position.0).abs() <= options.multi_click_distance &&
+                    (position.1 - last_position.1).abs() <= options.multi_click_distance;
+                let next = match previous.kind {
+                    GestureKind::Click => Some(GestureKind::DoubleClick),
+                    GestureKind::DoubleClick => Some(GestureKind::TripleClick),
+                    _ => None
+                };
+                if let Some(next) = next.filter(|_| near && start.saturating_sub(last_start) <= options.multi_click_interval) {
+                    previous.kind = next;
+                    previous.end = ev.timestamp;
+                    last_click = Some((start, position));
+                    continue;
+                }
+            }
+            last_click = Some((start, position));
+        } else {
+            last_click = None;
+        }
+
+        gestures.push(Gesture { kind, button, position, start, end: ev.timestamp });
+    }
+    gestures
+}
+
+/// the events of `Gesture.to_events` of all the gestures, in order
+pub(crate) fn semantic_events(gestures: &[Gesture], drag_steps: usize) -> Vec<SemanticEv> {
+    let mut evs: Vec<SemanticEv> = gestures.iter().flat_map(|gesture| gesture.to_events(drag_steps)).collect();
+    // stable, a gesture replayed longer than recorded may reach into the next one
+    evs.sort_by_key(|ev| ev.timestamp);
+    evs
+}
+// endregion
+
+// region action
+impl MouseAction {
+    /// Label the presses and releases: clicks, double and triple clicks (a click within
+    /// `multi_click_interval` and `multi_click_distance` of the one before), drags and long presses.
+    /// A press never released, or a release never pressed, is left out. The events should be in order.
+    pub fn gestures(&self, options: &GestureOptions) -> Vec<Gesture> {
+        recognize(&self.evs, options)
+    }
+
+    /// The events to replay the gestures of the action as intents, see `Gesture.to_events`.
+    /// `MousePlayer.set_semantic` plays them.
+    pub fn semantic_events(&self, options: &GestureOptions) -> Vec<SemanticEv> {
+        semantic_events(&self.gestures(options), options.drag_steps)
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ev(ev_name: MouseEventName, position: (i32, i32), timestamp: u64) -> MouseEv {
+        MouseEv { ev_name, position, timestamp }
+    }
+
+    #[test]
+    fn recognize_gestures() {
+        let action = MouseAction {
+            evs: vec![
+                // triple click, then a click too late for a 4th
+                ev(MouseEventName::LeftDown, (10, 10), 0),
+                ev(MouseEventName::LeftUp, (10, 10), 40),
+                ev(MouseEventName::LeftDown, (11, 10), 150),
+                ev(MouseEventName::LeftUp, (11, 10), 190),
+                ev(MouseEventName::LeftDown, (10, 12), 300),
+                ev(MouseEventName::LeftUp, (10, 12), 340),
+                ev(MouseEventName::LeftDown, (10, 10), 1000),
+                ev(MouseEventName::LeftUp, (10, 10), 1040),
+                // drag
+                ev(MouseEventName::LeftDown, (10, 10), 2000),
+                ev(MouseEventName::LeftUp, (200, 100), 2500),
+                // long press, and a release never pressed
+                ev(MouseEventName::RightDown, (50, 50), 3000),
+                ev(MouseEventName::RightUp, (52, 50), 4000),
+                ev(MouseEventName::MidUp, (0, 0), 4100),
+            ],
+            till: 5000,
+        };
+
+        let gestures = action.gestures(&GestureOptions::default());
+        let kinds: Vec<(GestureKind, u64, u64)> = gestures.iter().map(|g| (g.kind, g.start, g.end)).collect();
+        assert_eq!(kinds, vec![
+            (GestureKind::TripleClick, 0, 340),
+            (GestureKind::Click, 1000, 1040),
+            (GestureKind::Drag { to: (200, 100) }, 2000, 2500),
+            (GestureKind::LongPress, 3000, 4000),
+        ]);
+        assert_eq!(gestures[3].button, 2);
+    }
+
+    #[test]
+    fn semantic_replay() {
+        let double = Gesture { kind: GestureKind::DoubleClick, button: 1, position: (5, 5), start: 100, end: 400 };
+        let timestamps: Vec<u64> = double.to_events(10).iter().map(|ev| ev.timestamp).collect();
+        assert_eq!(timestamps, vec![100, 150, 200, 250]);
+
+        let drag = Gesture { kind: GestureKind::Drag { to: (100, 0) }, button: 1, position: (0, 0), start: 0, end: 1000 };
+        let evs = drag.to_events(4);
+        let path: Vec<(Option<MouseEventName>, i32, u64)> = evs.iter().map(|ev| (ev.ev_name, ev.position.0, ev.timestamp)).collect();
+        assert_eq!(path, vec![
+            (Some(MouseEventName::LeftDown), 0, 0),
+            (None, 16, 250),
+            (None, 50, 500),
+            (None, 84, 750),
+            (Some(MouseEventName::LeftUp), 100, 1000),
+        ]);
+    }
+
+    /// the player presses the right button before the moves, and releases it at the end
+    #[test]
+    fn replay_right_drag() {
+        let action = MouseAction {
+            evs: vec![
+                ev(MouseEventName::RightDown, (0, 0), 0),
+                ev(MouseEventName::RightUp, (100, 100), 500),
+            ],
+            till: 500,
+        };
+        let evs = action.semantic_events(&GestureOptions::default());
+        let played: Vec<(enigo::MouseButton, bool)> = evs.iter()
+            .filter_map(|ev| ev.ev_name.map(MouseMapper::parse_ev_name))
+            .collect();
+        assert!(matches!(played[..], [(enigo::MouseButton::Right, true), (enigo::MouseButton::Right, false)]));
+        assert_eq!(evs[0].ev_name, Some(MouseEventName::RightDown));
+        assert!(MouseMapper::parse_ev_name(MouseEventName::MidDown).1);
+    }
+}
+// endregion
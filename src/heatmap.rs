@@ -0,0 +1,381 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::{Error, MouseAction, MouseMapper};
+
+// region image
+/// how `MouseAction.heatmap` draws
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HeatmapOptions {
+    /// `[width, height]` of the image
+    pub size: [u32; 2],
+    /// `[width, height]` of the screen the image covers from `(0, 0)` (see `Metadata.screen_size`),
+    /// `None` to cover the positions of the action
+    pub screen: Option<[u32; 2]>,
+    /// radius in px of the image of the heat around each click
+    pub radius: u32,
+    /// draw the path of the mouse from event to event over the heat
+    pub paths: bool,
+}
+
+impl Default for HeatmapOptions {
+    fn default() -> HeatmapOptions {
+        HeatmapOptions {
+            size: [640, 360],
+            screen: None,
+            radius: 16,
+            paths: false,
+        }
+    }
+}
+
+/// an RGB image, see `MouseAction.heatmap`
+pub struct HeatmapImage {
+    pub width: u32,
+    pub height: u32,
+    /// `width * height` pixels of 3 bytes, row by row from the top
+    pub pixels: Vec<u8>,
+}
+
+/// the color of `heat` in `[0, 1]`: black, blue, cyan, green, yellow then red
+fn color(heat: f32) -> [u8; 3] {
+    const STOPS: [[f32; 3]; 6] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 255.0],
+        [0.0, 255.0, 255.0],
+        [0.0, 255.0, 0.0],
+        [255.0, 255.0, 0.0],
+        [255.0, 0.0, 0.0],
+    ];
+    let position = heat.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position.floor() as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let (from, to) = (STOPS[index], STOPS[index + 1]);
+    [0, 1, 2].map(|i| (from[i] + (to[i] - from[i]) * t).round() as u8)
+}
+
+impl HeatmapImage {
+    fn set(&mut self, x: i64, y: i64, rgb: [u8; 3]) {
+        if x >= 0 && y >= 0 && x < self.width as i64 && y < self.height as i64 {
+            let offset = (y as usize * self.width as usize + x as usize) * 3;
+            self.pixels[offset..offset + 3].copy_from_slice(&rgb);
+        }
+    }
+
+    /// The part of the segment in the image (`[0, width) x [0, height)`), by Liang-Barsky.
+    /// `None` if it is out of the image.
+    fn clip(&self, from: (f32, f32), to: (f32, f32)) -> Option<((i64, i64), (i64, i64))> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        // f64, the positions far out of the screen lose the pixels in f32
+        let (from, to) = ((from.0 as f64, from.1 as f64), (to.0 as f64, to.1 as f64));
+        let (right, bottom) = ((self.width - 1) as f64, (self.height - 1) as f64);
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let (mut enter, mut leave) = (0f64, 1f64);
+        // (p, q) of each edge: left, right, top, bottom
+        for (p, q) in [(-dx, from.0), (dx, right - from.0), (-dy, from.1), (dy, bottom - from.1)] {
+            if p == 0.0 {
+                // parallel to the edge
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                enter = enter.max(q / p);
+            } else {
+                leave = leave.min(q / p);
+            }
+        }
+        if enter > leave {
+            return None;
+        }
+        let at = |t: f64| ((from.0 + t * dx).round() as i64, (from.1 + t * dy).round() as i64);
+        Some((at(enter), at(leave)))
+    }
+
+    /// a line of Bresenham, `from` and `to` should be clipped (see `clip`) so that it does not walk out of the image
+    fn line(&mut self, from: (i64, i64), to: (i64, i64), rgb: [u8; 3]) {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (sx, sy) = (if x < to.0 { 1 } else { -1 }, if y < to.1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        loop {
+            self.set(x, y, rgb);
+            if (x, y) == to {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// binary PPM (`P6`)
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.pixels)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// PNG, 8 bit RGB, not compressed
+    pub fn write_png<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(PNG_SIGNATURE)?;
+
+        let mut header = vec![];
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // bit depth 8, color type 2 (RGB), deflate, no filter, no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+
+        // every row starts with the filter type 0 (none)
+        let row = self.width as usize * 3;
+        let mut scanlines = Vec::with_capacity((row + 1) * self.height as usize);
+        for y in 0..self.height as usize {
+            scanlines.push(0);
+            scanlines.extend_from_slice(&self.pixels[y * row..(y + 1) * row]);
+        }
+        write_chunk(&mut writer, b"IDAT", &zlib_stored(&scanlines))?;
+        write_chunk(&mut writer, b"IEND", &[])?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// save to `path`, `.ppm` or `.png`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let extension = path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "ppm" => self.write_ppm(BufWriter::new(File::create(path)?)),
+            "png" => self.write_png(BufWriter::new(File::create(path)?)),
+            _ => Err(Error::UnknownFormat(extension))
+        }
+    }
+}
+// endregion
+
+// region png
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// length, type, data and the crc of type and data
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<(), Error> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut checked = kind.to_vec();
+    checked.extend_from_slice(data);
+    writer.write_all(&checked)?;
+    writer.write_all(&crc32(&checked).to_be_bytes())?;
+    Ok(())
+}
+
+/// a zlib stream of stored (not compressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32K window, no dictionary, 0x7801 is a multiple of 31
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        // BFINAL on the last block, BTYPE 00
+        stream.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let length = block.len() as u16;
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+// endregion
+
+// region action
+impl MouseAction {
+    /// Render where the buttons are pressed as a heatmap, the hottest spot is red.
+    /// The heat of each press falls off to 0 at `options.radius`.
+    pub fn heatmap(&self, options: &HeatmapOptions) -> HeatmapImage {
+        let [width, height] = options.size;
+        let screen = options.screen.unwrap_or_else(|| {
+            let right = self.evs.iter().map(|ev| ev.position.0).max().unwrap_or(0).max(0) as u32 + 1;
+            let bottom = self.evs.iter().map(|ev| ev.position.1).max().unwrap_or(0).max(0) as u32 + 1;
+            [right, bottom]
+        });
+        // a position of the screen in the image
+        let scale = (width as f32 / screen[0].max(1) as f32, height as f32 / screen[1].max(1) as f32);
+        let to_image = |(x, y): (i32, i32)| (x as f32 * scale.0, y as f32 * scale.1);
+
+        let mut heat = vec![0f32; width as usize * height as usize];
+        let radius = options.radius.max(1) as f32;
+        for ev in self.evs.iter() {
+            if !MouseMapper::ev_name_to_dq(ev.ev_name).1 {
+                continue;
+            }
+            let (cx, cy) = to_image(ev.position);
+            let x_range = ((cx - radius).floor().max(0.0) as u32)..((cx + radius).ceil().max(0.0) as u32).min(width);
+            for x in x_range {
+                let y_range = ((cy - radius).floor().max(0.0) as u32)..((cy + radius).ceil().max(0.0) as u32).min(height);
+                for y in y_range {
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    // biweight kernel
+                    let d2 = (dx * dx + dy * dy) / (radius * radius);
+                    if d2 < 1.0 {
+                        heat[y as usize * width as usize + x as usize] += (1.0 - d2) * (1.0 - d2);
+                    }
+                }
+            }
+        }
+
+        let max = heat.iter().cloned().fold(0.0, f32::max);
+        let mut image = HeatmapImage {
+            width,
+            height,
+            pixels: Vec::with_capacity(heat.len() * 3),
+        };
+        for value in heat {
+            image.pixels.extend_from_slice(&color(if max > 0.0 { value / max } else { 0.0 }));
+        }
+
+        if options.paths {
+            for pair in self.evs.windows(2) {
+                if let Some((from, to)) = image.clip(to_image(pair[0].position), to_image(pair[1].position)) {
+                    image.line(from, to, [255, 255, 255]);
+                }
+            }
+        }
+        image
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MouseEv, MouseEventName};
+
+    fn action() -> MouseAction {
+        MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (100, 100), timestamp: 0 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (100, 100), timestamp: 50 },
+                MouseEv { ev_name: MouseEventName::RightDown, position: (300, 100), timestamp: 500 },
+                MouseEv { ev_name: MouseEventName::RightUp, position: (300, 100), timestamp: 550 },
+            ],
+            till: 600,
+        }
+    }
+
+    fn pixel(image: &HeatmapImage, x: u32, y: u32) -> [u8; 3] {
+        let offset = ((y * image.width + x) * 3) as usize;
+        [image.pixels[offset], image.pixels[offset + 1], image.pixels[offset + 2]]
+    }
+
+    #[test]
+    fn heatmap() {
+        let options = HeatmapOptions { size: [40, 20], screen: Some([400, 200]), radius: 4, paths: false };
+        let image = action().heatmap(&options);
+        assert_eq!(image.pixels.len(), 40 * 20 * 3);
+        assert_eq!(pixel(&image, 10, 10), [255, 0, 0]);
+        assert_eq!(pixel(&image, 30, 10), [255, 0, 0]);
+        assert_eq!(pixel(&image, 20, 10), [0, 0, 0]);
+
+        let image = action().heatmap(&HeatmapOptions { paths: true, ..options });
+        assert_eq!(pixel(&image, 20, 10), [255, 255, 255]);
+    }
+
+    /// only the part of a path in the image is walked
+    #[test]
+    fn clipped_paths() {
+        let image = HeatmapImage { width: 40, height: 20, pixels: vec![0; 40 * 20 * 3] };
+        assert_eq!(image.clip((-1e9, 10.0), (20.0, 10.0)), Some(((0, 10), (20, 10))));
+        assert_eq!(image.clip((-10.0, -10.0), (50.0, 50.0)), Some(((0, 0), (19, 19))));
+        assert_eq!(image.clip((50.0, 0.0), (60.0, 30.0)), None);
+        assert_eq!(image.clip((5.0, 5.0), (6.0, 7.0)), Some(((5, 5), (6, 7))));
+
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (i32::MIN, 100), timestamp: 0 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (i32::MAX, 100), timestamp: 50 },
+            ],
+            till: 50,
+        };
+        let image = action.heatmap(&HeatmapOptions { size: [40, 20], screen: Some([400, 200]), radius: 4, paths: true });
+        assert_eq!(pixel(&image, 0, 10), [255, 255, 255]);
+        assert_eq!(pixel(&image, 39, 10), [255, 255, 255]);
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn ppm_and_png() {
+        let image = action().heatmap(&HeatmapOptions { size: [300, 250], ..HeatmapOptions::default() });
+
+        let mut ppm = vec![];
+        image.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n300 250\n255\n"));
+        assert_eq!(ppm.len(), 15 + 300 * 250 * 3);
+
+        let mut png = vec![];
+        image.write_png(&mut png).unwrap();
+        assert!(png.starts_with(PNG_SIGNATURE));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]));
+
+        // the IDAT holds the rows as they are, in 2 stored blocks
+        let idat = &png[33..png.len() - 12];
+        let length = u32::from_be_bytes([idat[0], idat[1], idat[2], idat[3]]) as usize;
+        assert_eq!(&idat[4..8], b"IDAT");
+        let stream = &idat[8..8 + length];
+        let mut data = vec![];
+        let mut offset = 2;
+        loop {
+            let last = stream[offset] == 1;
+            let size = u16::from_le_bytes([stream[offset + 1], stream[offset + 2]]) as usize;
+            data.extend_from_slice(&stream[offset + 5..offset + 5 + size]);
+            offset += 5 + size;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(data.len(), (300 * 3 + 1) * 250);
+        assert_eq!(&data[1..1 + 300 * 3], &image.pixels[..300 * 3]);
+        assert_eq!(u32::from_be_bytes([stream[offset], stream[offset + 1], stream[offset + 2], stream[offset + 3]]), adler32(&data));
+    }
+}
+// endregion
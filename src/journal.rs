@@ -0,0 +1,358 @@
+use std::fs::File;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use serde_json::to_writer as json_write;
+use crate::{CombinedAction, CombinedEv, Error, FORMAT_VERSION, KeyboardAction, KeyboardActionSerializable, KeyboardEv, KeyboardEvSerializable, KeyboardMapper, KeyMap, Metadata, MouseAction, MouseActionSerializable, MouseEv, MouseEvSerializable, MouseMapper, SkippedEvent};
+
+// region line
+/// the first line of a journal
+#[derive(Deserialize, Serialize)]
+pub struct JournalHeader {
+    pub format_version: u32,
+    /// `keyboard`, `mouse` or `combined`
+    pub kind: String,
+    pub meta: Metadata,
+}
+
+/// the last line of a journal, missing if the record did not end well
+#[derive(Deserialize, Serialize)]
+struct JournalEnd {
+    till: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JournalLine<T> {
+    End(JournalEnd),
+    Event(T),
+}
+
+/// an event line of a `combined` journal, told apart by its fields
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CombinedLine {
+    Keyboard(KeyboardEvSerializable),
+    Mouse(MouseEvSerializable),
+}
+
+fn line_error(line: usize, err: serde_json::Error) -> Error {
+    Error::Parse {
+        line,
+        column: err.column(),
+        message: err.to_string(),
+    }
+}
+// endregion
+
+// region writer
+/// Write a record as JSON Lines, one event per line as soon as it happens:
+/// a `JournalHeader` line, the events, and a `{"till":...}` line at the end.
+/// The writer is flushed after every line, so a crash loses no more than the line being written.
+pub struct JournalWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JournalWriter<W> {
+    /// write the header, `kind` is `keyboard`, `mouse` or `combined`
+    pub fn new(writer: W, kind: &str, meta: &Metadata) -> Result<JournalWriter<W>, Error> {
+        let mut journal = JournalWriter { writer };
+        journal.write_line(&JournalHeader {
+            format_version: FORMAT_VERSION,
+            kind: String::from(kind),
+            meta: meta.clone(),
+        })?;
+        Ok(journal)
+    }
+
+    fn write_line<T: Serialize>(&mut self, line: &T) -> Result<(), Error> {
+        json_write(&mut self.writer, line)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// It fails with `Error::UnknownKey` for a key without `code` in frontend.
+    pub fn write_keyboard_ev(&mut self, ev: &KeyboardEv) -> Result<(), Error> {
+        self.write_line(&KeyboardEvSerializable {
+            code: String::from(KeyboardMapper::dq_to_front_checked(ev.code)?),
+            press: ev.press,
+            timestamp: ev.timestamp,
+        })
+    }
+
+    pub fn write_mouse_ev(&mut self, ev: &MouseEv) -> Result<(), Error> {
+        self.write_line(&MouseEvSerializable {
+            ev_name: MouseMapper::ev_name_to_number(ev.ev_name),
+            position: [ev.position.0, ev.position.1],
+            timestamp: ev.timestamp,
+        })
+    }
+
+    /// write the end line and give the writer back
+    pub fn finish(mut self, till: u64) -> Result<W, Error> {
+        self.write_line(&JournalEnd { till })?;
+        Ok(self.writer)
+    }
+}
+
+/// the journal of a recorder, with the error of its first failed write
+pub(crate) struct RecordJournal {
+    writer: Option<JournalWriter<File>>,
+    error: Option<Error>,
+}
+
+/// the journal shared by the listeners of a recorder
+pub(crate) type SharedJournal = Arc<Mutex<RecordJournal>>;
+
+pub(crate) fn share_journal(writer: Option<JournalWriter<File>>) -> SharedJournal {
+    Arc::new(Mutex::new(RecordJournal { writer, error: None }))
+}
+
+/// Append to the journal of a recorder. After a failed write the journal is dropped
+/// (the record goes on in memory) and the error is kept for `finish_journal`.
+pub(crate) fn append_journal<F>(journal: &SharedJournal, write: F)
+    where F: FnOnce(&mut JournalWriter<File>) -> Result<(), Error> {
+    let mut journal = journal.lock().unwrap();
+    if let Some(writer) = journal.writer.as_mut() {
+        if let Err(err) = write(writer) {
+            journal.writer = None;
+            journal.error = Some(err);
+        }
+    }
+}
+
+/// write the end line, or give the error of the first failed write
+pub(crate) fn finish_journal(journal: &SharedJournal, till: u64) -> Result<(), Error> {
+    let mut journal = journal.lock().unwrap();
+    if let Some(err) = journal.error.take() {
+        return Err(err);
+    }
+    if let Some(writer) = journal.writer.take() {
+        writer.finish(till)?;
+    }
+    Ok(())
+}
+// endregion
+
+// region reader
+/// Read the events of a journal.
+/// A journal cut off by a crash is read up to its last whole line,
+/// `till` is then the timestamp of the last event.
+fn read_journal<T, R>(reader: R, kind: &str) -> Result<(Vec<T>, Option<u64>, Metadata), Error>
+    where T: for<'de> Deserialize<'de>, R: BufRead {
+    let mut lines = reader.lines().enumerate();
+
+    let header: JournalHeader = match lines.next() {
+        Some((_, line)) => serde_json::from_str(&line?).map_err(|err| line_error(1, err))?,
+        None => return Err(Error::Parse { line: 1, column: 0, message: String::from("empty journal") })
+    };
+    if header.format_version > FORMAT_VERSION {
+        return Err(Error::Parse {
+            line: 1,
+            column: 0,
+            message: format!("unsupported format version {}, the latest is {}", header.format_version, FORMAT_VERSION),
+        });
+    }
+    if header.kind != kind {
+        return Err(Error::Parse {
+            line: 1,
+            column: 0,
+            message: format!("expect a {} record, found a {} record", kind, header.kind),
+        });
+    }
+
+    let mut evs = vec![];
+    let mut till = None;
+    let mut broken = None;
+    for (index, line) in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // only the last line may be broken
+        if let Some(err) = broken.take() {
+            return Err(err);
+        }
+        match serde_json::from_str::<JournalLine<T>>(&line) {
+            Ok(JournalLine::Event(ev)) => evs.push(ev),
+            Ok(JournalLine::End(end)) => {
+                till = Some(end.till);
+                break;
+            }
+            Err(err) => broken = Some(line_error(index + 1, err))
+        }
+    }
+
+    Ok((evs, till, header.meta))
+}
+
+impl KeyboardAction {
+    pub fn write_journal<W: Write>(&self, writer: W, meta: &Metadata) -> Result<(), Error> {
+        let mut journal = JournalWriter::new(writer, "keyboard", meta)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            journal.write_keyboard_ev(ev).map_err(|reason| SkippedEvent { index, reason }.into_error())?;
+        }
+        journal.finish(self.till)?;
+        Ok(())
+    }
+
+    /// Rebuild an action from a journal, which may be cut off (see `JournalWriter`).
+    /// Events with unknown codes are left out, as `from_string` does.
+    pub fn read_journal<R: BufRead>(reader: R) -> Result<(KeyboardAction, Metadata), Error> {
+        let (evs, till, meta) = read_journal::<KeyboardEvSerializable, R>(reader, "keyboard")?;
+        let till = till.unwrap_or_else(|| evs.iter().map(|ev| ev.timestamp).max().unwrap_or(0));
+        let source = KeyboardActionSerializable { evs, till };
+        Ok((KeyboardAction::from_serializable(&source, &KeyMap::new()).0, meta))
+    }
+}
+
+impl MouseAction {
+    pub fn write_journal<W: Write>(&self, writer: W, meta: &Metadata) -> Result<(), Error> {
+        let mut journal = JournalWriter::new(writer, "mouse", meta)?;
+        for ev in self.evs.iter() {
+            journal.write_mouse_ev(ev)?;
+        }
+        journal.finish(self.till)?;
+        Ok(())
+    }
+
+    /// Rebuild an action from a journal, which may be cut off (see `JournalWriter`).
+    /// Events with unknown `ev_name` are left out, as `from_string` does.
+    pub fn read_journal<R: BufRead>(reader: R) -> Result<(MouseAction, Metadata), Error> {
+        let (evs, till, meta) = read_journal::<MouseEvSerializable, R>(reader, "mouse")?;
+        let till = till.unwrap_or_else(|| evs.iter().map(|ev| ev.timestamp).max().unwrap_or(0));
+        let source = MouseActionSerializable { evs, till };
+        Ok((MouseAction::from_serializable(&source).0, meta))
+    }
+}
+
+impl CombinedAction {
+    pub fn write_journal<W: Write>(&self, writer: W, meta: &Metadata) -> Result<(), Error> {
+        let mut journal = JournalWriter::new(writer, "combined", meta)?;
+        for (index, ev) in self.evs.iter().enumerate() {
+            match ev {
+                CombinedEv::Keyboard(ev) => journal.write_keyboard_ev(ev).map_err(|reason| SkippedEvent { index, reason }.into_error())?,
+                CombinedEv::Mouse(ev) => journal.write_mouse_ev(ev)?,
+            }
+        }
+        journal.finish(self.till)?;
+        Ok(())
+    }
+
+    /// see `KeyboardAction.read_journal`, the events keep the order of the lines.
+    pub fn read_journal<R: BufRead>(reader: R) -> Result<(CombinedAction, Metadata), Error> {
+        let (lines, till, meta) = read_journal::<CombinedLine, R>(reader, "combined")?;
+        let mut action = CombinedAction { evs: vec![], till: 0 };
+        let mut last = 0;
+        for line in lines {
+            let ev = match line {
+                CombinedLine::Keyboard(ev) => KeyboardMapper::front_to_dq(&ev.code).map(|code| CombinedEv::Keyboard(KeyboardEv {
+                    code,
+                    press: ev.press,
+                    timestamp: ev.timestamp,
+                })),
+                CombinedLine::Mouse(ev) => MouseMapper::number_to_ev_name(ev.ev_name).ok().map(|ev_name| CombinedEv::Mouse(MouseEv {
+                    ev_name,
+                    position: (ev.position[0], ev.position[1]),
+                    timestamp: ev.timestamp,
+                })),
+            };
+            if let Some(ev) = ev {
+                last = last.max(ev.timestamp());
+                action.evs.push(ev);
+            }
+        }
+        action.till = till.unwrap_or(last);
+        Ok((action, meta))
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+    use device_query::Keycode;
+    use crate::MouseEventName;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("toca-{}-{}", std::process::id(), name))
+    }
+
+    fn keyboard_action() -> KeyboardAction {
+        KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 10 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 90 },
+                KeyboardEv { code: Keycode::B, press: true, timestamp: 150 },
+            ],
+            till: 200,
+        }
+    }
+
+    #[test]
+    fn keyboard_journal() {
+        let mut buffer = vec![];
+        keyboard_action().write_journal(&mut buffer, &Metadata::new()).unwrap();
+        let journal = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = journal.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("{\"format_version\":2,\"kind\":\"keyboard\""));
+        assert_eq!(lines[1], "{\"code\":\"KeyA\",\"press\":true,\"timestamp\":10}");
+        assert_eq!(lines[4], "{\"till\":200}");
+
+        let (action, meta) = KeyboardAction::read_journal(journal.as_bytes()).unwrap();
+        assert_eq!(action.evs.len(), 3);
+        assert_eq!(action.till, 200);
+        assert_eq!(meta.host_os, std::env::consts::OS);
+    }
+
+    /// a journal cut off in the middle of a line keeps the whole lines before
+    #[test]
+    fn truncated_journal() {
+        let mut buffer = vec![];
+        keyboard_action().write_journal(&mut buffer, &Metadata::new()).unwrap();
+        let journal = String::from_utf8(buffer).unwrap();
+        let cut = journal.find("{\"code\":\"KeyB\"").unwrap() + 12;
+
+        let (action, _) = KeyboardAction::read_journal(&journal.as_bytes()[..cut]).unwrap();
+        assert_eq!(action.evs.len(), 2);
+        assert_eq!(action.till, 90);
+
+        let broken = journal.replacen("\"press\":false", "\"press\":", 1);
+        assert!(matches!(KeyboardAction::read_journal(broken.as_bytes()), Err(Error::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn mouse_journal() {
+        let action = MouseAction {
+            evs: vec![MouseEv { ev_name: MouseEventName::RightDown, position: (3, 4), timestamp: 10 }],
+            till: 20,
+        };
+        let mut buffer = vec![];
+        action.write_journal(&mut buffer, &Metadata::default()).unwrap();
+
+        let (again, _) = MouseAction::read_journal(buffer.as_slice()).unwrap();
+        assert_eq!(again.evs[0].position, (3, 4));
+        assert_eq!(again.till, 20);
+        assert!(matches!(KeyboardAction::read_journal(buffer.as_slice()), Err(Error::Parse { line: 1, .. })));
+    }
+
+    /// the first failed write stops the journal and is given back at the end
+    #[test]
+    fn failed_journal() {
+        let path = temp_path("failed_journal.jsonl");
+        let writer = JournalWriter::new(File::create(&path).unwrap(), "keyboard", &Metadata::default()).unwrap();
+        let journal = share_journal(Some(writer));
+        append_journal(&journal, |_| Err(Error::Busy));
+        append_journal(&journal, |_| panic!("the journal is dropped"));
+        assert!(matches!(finish_journal(&journal, 100), Err(Error::Busy)));
+
+        let read_only = File::open(&path).unwrap();
+        assert!(JournalWriter::new(read_only, "keyboard", &Metadata::default()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+// endregion
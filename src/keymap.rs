@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use device_query::Keycode;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str as json_parse, to_string as json_stringify};
+use crate::{Error, KeyboardMapper};
+
+// region key map
+/// A runtime table to translate or suppress keys, e.g. play `CapsLock` as `Escape`.
+/// Keys are named by their `code` in frontend (see `KeyboardMapper::dq_to_front`),
+/// keys without a frontend code are named by the `Display` of their `Keycode`.
+#[derive(Clone, Debug, Default)]
+pub struct KeyMap {
+    /// source code => target code
+    remap: HashMap<String, String>,
+    /// codes to drop
+    suppress: HashSet<String>,
+}
+
+/// the form of `KeyMap` in `json` / `toml`
+/// ```toml
+/// suppress = ["Insert"]
+///
+/// [remap]
+/// CapsLock = "Escape"
+/// MetaLeft = "ControlLeft"
+/// ```
+#[derive(Deserialize, Serialize, Default)]
+pub struct KeyMapSerializable {
+    /// codes to drop
+    #[serde(default)]
+    pub suppress: Vec<String>,
+    /// source code => target code
+    #[serde(default)]
+    pub remap: HashMap<String, String>,
+}
+
+impl KeyMap {
+    pub fn new() -> KeyMap {
+        KeyMap {
+            remap: HashMap::new(),
+            suppress: HashSet::new(),
+        }
+    }
+
+    /// translate the key named `from` into the key named `to`.
+    /// fails if `to` is not a known frontend code.
+    pub fn remap(&mut self, from: &str, to: &str) -> Result<(), Error> {
+        match KeyboardMapper::front_to_dq(to) {
+            Some(_) => {
+                self.suppress.remove(from);
+                self.remap.insert(String::from(from), String::from(to));
+                Ok(())
+            }
+            None => Err(Error::UnknownKey(String::from(to)))
+        }
+    }
+
+    /// drop the key named `code`.
+    pub fn suppress(&mut self, code: &str) {
+        self.remap.remove(code);
+        self.suppress.insert(String::from(code));
+    }
+
+    /// translate a code, `None` means the key is suppressed.
+    pub fn translate_code<'a>(&'a self, code: &'a str) -> Option<&'a str> {
+        if self.suppress.contains(code) {
+            return None;
+        }
+        match self.remap.get(code) {
+            Some(target) => Some(target.as_str()),
+            None => Some(code)
+        }
+    }
+
+    /// translate a key, `None` means the key is suppressed.
+    pub fn translate(&self, key: Keycode) -> Option<Keycode> {
+        if self.remap.is_empty() && self.suppress.is_empty() {
+            return Some(key);
+        }
+
+        let name = match KeyboardMapper::dq_to_front(key) {
+            Some(code) => String::from(code),
+            None => key.to_string()
+        };
+
+        match self.translate_code(&name) {
+            Some(target) if target == name => Some(key),
+            // targets are checked when they are inserted
+            Some(target) => KeyboardMapper::front_to_dq(target),
+            None => None
+        }
+    }
+
+    pub fn from_serializable(source: KeyMapSerializable) -> Result<KeyMap, Error> {
+        let mut keymap = KeyMap::new();
+        for (from, to) in source.remap.iter() {
+            keymap.remap(from, to)?;
+        }
+        for code in source.suppress.iter() {
+            keymap.suppress(code);
+        }
+        Ok(keymap)
+    }
+
+    pub fn to_serializable(&self) -> KeyMapSerializable {
+        let mut suppress: Vec<String> = self.suppress.iter().cloned().collect();
+        suppress.sort();
+        KeyMapSerializable {
+            suppress,
+            remap: self.remap.clone(),
+        }
+    }
+
+    pub fn from_json(string_source: &str) -> Result<KeyMap, Error> {
+        match json_parse::<KeyMapSerializable>(string_source) {
+            Ok(source) => KeyMap::from_serializable(source),
+            Err(err) => Err(Error::from(err))
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        match json_stringify(&self.to_serializable()) {
+            Ok(s) => Ok(s),
+            Err(err) => Err(Error::from(err))
+        }
+    }
+
+    pub fn from_toml(string_source: &str) -> Result<KeyMap, Error> {
+        match toml::from_str::<KeyMapSerializable>(string_source) {
+            Ok(source) => KeyMap::from_serializable(source),
+            Err(err) => Err(Error::from(err))
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, Error> {
+        match toml::to_string(&self.to_serializable()) {
+            Ok(s) => Ok(s),
+            Err(err) => Err(Error::from(err))
+        }
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::KeyboardAction;
+
+    #[test]
+    fn keymap_from_json() {
+        let keymap = KeyMap::from_json("{\"remap\":{\"CapsLock\":\"Escape\",\"MetaLeft\":\"ControlLeft\"},\"suppress\":[\"Insert\"]}").unwrap();
+
+        assert_eq!(keymap.translate(Keycode::CapsLock), Some(Keycode::Escape));
+        assert_eq!(keymap.translate(Keycode::Meta), Some(Keycode::LControl));
+        assert_eq!(keymap.translate(Keycode::Insert), None);
+        assert_eq!(keymap.translate(Keycode::A), Some(Keycode::A));
+    }
+
+    #[test]
+    fn keymap_from_toml() {
+        let keymap = KeyMap::from_toml("suppress = [\"KeyQ\"]\n\n[remap]\nCapsLock = \"Escape\"\n").unwrap();
+
+        assert_eq!(keymap.translate(Keycode::CapsLock), Some(Keycode::Escape));
+        assert_eq!(keymap.translate(Keycode::Q), None);
+        assert_eq!(KeyMap::from_toml(&keymap.to_toml().unwrap()).unwrap().translate(Keycode::Q), None);
+    }
+
+    #[test]
+    fn keymap_unknown_target() {
+        assert!(KeyMap::from_json("{\"remap\":{\"CapsLock\":\"Nothing\"}}").is_err());
+    }
+
+    /// unusual codes in the source can be translated before they are parsed
+    #[test]
+    fn keymap_from_string() {
+        let mut keymap = KeyMap::new();
+        keymap.remap("OSLeft", "MetaLeft").unwrap();
+        keymap.suppress("KeyB");
+
+        let action = KeyboardAction::from_string_with_keymap("{\"evs\":[{\"code\":\"OSLeft\",\"press\":true,\"timestamp\":10},{\"code\":\"KeyB\",\"press\":true,\"timestamp\":20}],\"till\":30}", &keymap).unwrap();
+        assert_eq!(action.evs.len(), 1);
+        assert_eq!(action.evs[0].code, Keycode::Meta);
+
+        let mut keymap = KeyMap::new();
+        keymap.remap("MetaLeft", "ControlLeft").unwrap();
+        let again = KeyboardAction::from_string(&action.to_string_with_keymap(&keymap).unwrap()).unwrap();
+        assert_eq!(again.evs[0].code, Keycode::LControl);
+    }
+}
+// endregion
@@ -0,0 +1,200 @@
+use device_query::Keycode;
+use enigo::Key;
+use crate::KeyboardMapper;
+
+// region keyboard layout
+/// the printable keys in the order of the layout tables below,
+/// from left to right, from top to bottom
+pub(crate) const PRINTABLE_KEYS: [Keycode; 47] = [
+    Keycode::Grave, Keycode::Key1, Keycode::Key2, Keycode::Key3, Keycode::Key4, Keycode::Key5,
+    Keycode::Key6, Keycode::Key7, Keycode::Key8, Keycode::Key9, Keycode::Key0, Keycode::Minus, Keycode::Equal,
+    Keycode::Q, Keycode::W, Keycode::E, Keycode::R, Keycode::T, Keycode::Y, Keycode::U, Keycode::I,
+    Keycode::O, Keycode::P, Keycode::LeftBracket, Keycode::RightBracket, Keycode::BackSlash,
+    Keycode::A, Keycode::S, Keycode::D, Keycode::F, Keycode::G, Keycode::H, Keycode::J, Keycode::K,
+    Keycode::L, Keycode::Semicolon, Keycode::Apostrophe,
+    Keycode::Z, Keycode::X, Keycode::C, Keycode::V, Keycode::B, Keycode::N, Keycode::M,
+    Keycode::Comma, Keycode::Dot, Keycode::Slash,
+];
+
+/// Common keyboard layouts, used to translate between the physical position of a key
+/// and the character it types.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    /// US QWERTY, the layout `KeyboardMapper` assumes
+    Qwerty,
+    /// French AZERTY
+    Azerty,
+    /// German QWERTZ
+    Qwertz,
+    /// US Dvorak
+    Dvorak,
+    /// Colemak
+    Colemak,
+}
+
+impl KeyboardLayout {
+    /// characters of `PRINTABLE_KEYS` as `(unshifted, shifted)`, `\0` means nothing is typed
+    fn table(&self) -> (&'static str, &'static str) {
+        match self {
+            KeyboardLayout::Qwerty => (
+                "`1234567890-=qwertyuiop[]\\asdfghjkl;'zxcvbnm,./",
+                "~!@#$%^&*()_+QWERTYUIOP{}|ASDFGHJKL:\"ZXCVBNM<>?",
+            ),
+            KeyboardLayout::Azerty => (
+                "²&é\"'(-è_çà)=azertyuiop^$*qsdfghjklmùwxcvbn,;:!",
+                "\u{0}1234567890°+AZERTYUIOP¨£µQSDFGHJKLM%WXCVBN?./§",
+            ),
+            KeyboardLayout::Qwertz => (
+                "^1234567890ß´qwertzuiopü+#asdfghjklöäyxcvbnm,.-",
+                "°!\"§$%&/()=?`QWERTZUIOPÜ*'ASDFGHJKLÖÄYXCVBNM;:_",
+            ),
+            KeyboardLayout::Dvorak => (
+                "`1234567890[]',.pyfgcrl/=\\aoeuidhtns-;qjkxbmwvz",
+                "~!@#$%^&*(){}\"<>PYFGCRL?+|AOEUIDHTNS_:QJKXBMWVZ",
+            ),
+            KeyboardLayout::Colemak => (
+                "`1234567890-=qwfpgjluy;[]\\arstdhneio'zxcvbkm,./",
+                "~!@#$%^&*()_+QWFPGJLUY:{}|ARSTDHNEIO\"ZXCVBKM<>?",
+            ),
+        }
+    }
+
+    /// the character typed by the key at the physical position `key` in this layout.
+    /// only printable keys (not `Space`, `Enter` etc.) are in the table.
+    pub fn key_to_char(&self, key: Keycode, shift: bool) -> Option<char> {
+        let index = PRINTABLE_KEYS.iter().position(|k| *k == key)?;
+        let (normal, shifted) = self.table();
+        let row = if shift { shifted } else { normal };
+        match row.chars().nth(index) {
+            Some('\0') | None => None,
+            Some(c) => Some(c),
+        }
+    }
+
+    /// the physical key (and if `Shift` is needed) to type `c` in this layout.
+    pub fn char_to_key(&self, c: char) -> Option<(Keycode, bool)> {
+        if c == '\0' {
+            return None;
+        }
+        let (normal, shifted) = self.table();
+        if let Some(index) = normal.chars().position(|x| x == c) {
+            return Some((PRINTABLE_KEYS[index], false));
+        }
+        if let Some(index) = shifted.chars().position(|x| x == c) {
+            return Some((PRINTABLE_KEYS[index], true));
+        }
+        None
+    }
+
+    /// the key to press in layout `to` to type what `key` types in this layout, e.g.
+    /// `KeyQ` on QWERTY is `KeyA` on AZERTY.
+    pub fn translate(&self, key: Keycode, to: KeyboardLayout) -> Option<Keycode> {
+        let c = self.key_to_char(key, false)?;
+        match to.char_to_key(c) {
+            Some((target, false)) => Some(target),
+            _ => None
+        }
+    }
+}
+// endregion
+
+// region playback mode
+/// How `KeyboardPlayer` turns the physical keys in a record into keys for the simulator.
+///
+/// `Enigo` types `Key::Layout(c)` with the layout of the *current* machine, so a record made on
+/// another layout needs one of the layout-aware modes to be played back faithfully.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// keys are sent through `KeyboardMapper::dq_to_enigo` (printable keys as their US QWERTY characters)
+    Direct,
+    /// the key at the same physical position is pressed, whatever it types on this machine.
+    /// the printable keys are sent as the raw code of their position (see `KeyboardMapper::dq_to_position`).
+    Physical,
+    /// the character the key typed when it was recorded is typed again, wherever it is on this machine.
+    /// the value is the layout the record was made with; the `Shift` state of the record is respected.
+    Character(KeyboardLayout),
+}
+
+impl Default for PlaybackMode {
+    fn default() -> Self {
+        PlaybackMode::Direct
+    }
+}
+
+impl PlaybackMode {
+    /// `Keycode` => `Key` in `Enigo` under the mode, `shift` is if `Shift` is held in the record.
+    /// The keys the mode does not change go through `KeyboardMapper::dq_to_enigo`
+    /// (or `dq_to_enigo_compatible` if `compatible`).
+    pub fn to_enigo(&self, code: Keycode, shift: bool, compatible: bool) -> Option<Key> {
+        let key = match self {
+            PlaybackMode::Direct => None,
+            PlaybackMode::Physical => KeyboardMapper::dq_to_position(code),
+            PlaybackMode::Character(layout) => layout.key_to_char(code, shift).map(Key::Layout),
+        };
+        match (key, compatible) {
+            (Some(key), _) => Some(key),
+            (None, true) => KeyboardMapper::dq_to_enigo_compatible(code),
+            (None, false) => KeyboardMapper::dq_to_enigo(code),
+        }
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LAYOUTS: [KeyboardLayout; 5] = [
+        KeyboardLayout::Qwerty,
+        KeyboardLayout::Azerty,
+        KeyboardLayout::Qwertz,
+        KeyboardLayout::Dvorak,
+        KeyboardLayout::Colemak,
+    ];
+
+    /// every table has a character (or a `\0`) for every printable key
+    #[test]
+    fn layout_table_size() {
+        for layout in LAYOUTS {
+            let (normal, shifted) = layout.table();
+            assert_eq!(normal.chars().count(), PRINTABLE_KEYS.len(), "{:?}", layout);
+            assert_eq!(shifted.chars().count(), PRINTABLE_KEYS.len(), "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn layout_char_to_key() {
+        for layout in LAYOUTS {
+            for key in PRINTABLE_KEYS {
+                for shift in [false, true] {
+                    if let Some(c) = layout.key_to_char(key, shift) {
+                        assert_eq!(layout.char_to_key(c), Some((key, shift)), "{:?} {}", layout, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn layout_translate() {
+        assert_eq!(KeyboardLayout::Qwerty.key_to_char(Keycode::Key1, true), Some('!'));
+        assert_eq!(KeyboardLayout::Azerty.key_to_char(Keycode::Q, false), Some('a'));
+        assert_eq!(KeyboardLayout::Qwerty.translate(Keycode::Q, KeyboardLayout::Azerty), Some(Keycode::A));
+        assert_eq!(KeyboardLayout::Qwerty.translate(Keycode::Y, KeyboardLayout::Qwertz), Some(Keycode::Z));
+        assert_eq!(KeyboardLayout::Qwerty.translate(Keycode::Space, KeyboardLayout::Dvorak), None);
+    }
+
+    /// the physical mode sends raw codes of the positions, the character mode characters
+    #[test]
+    fn playback_mode() {
+        for key in PRINTABLE_KEYS {
+            assert!(matches!(PlaybackMode::Physical.to_enigo(key, true, false), Some(Key::Raw(_))), "{:?}", key);
+        }
+        assert_ne!(PlaybackMode::Physical.to_enigo(Keycode::Q, false, false), PlaybackMode::Physical.to_enigo(Keycode::A, false, false));
+        assert_eq!(PlaybackMode::Physical.to_enigo(Keycode::Escape, false, false), Some(Key::Escape));
+        assert_eq!(PlaybackMode::Character(KeyboardLayout::Azerty).to_enigo(Keycode::Q, false, false), Some(Key::Layout('a')));
+        assert_eq!(PlaybackMode::Direct.to_enigo(Keycode::Q, false, false), Some(Key::Layout('q')));
+    }
+}
+// endregion
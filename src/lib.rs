@@ -1,9 +1,51 @@
+pub mod error;
 pub mod mapper;
+pub mod keymap;
+pub mod layout;
+pub mod text;
+pub mod format;
+pub mod file;
+pub mod binary;
+pub mod journal;
+pub mod csv;
+pub mod xdotool;
+pub mod browser;
+pub mod dsl;
+pub mod combined;
+pub mod diff;
+pub mod stats;
+pub mod heatmap;
+pub mod shortcut;
+pub mod gesture;
+pub mod validate;
+pub mod normalize;
+pub mod edit;
 pub mod record;
 pub mod display;
 pub mod utils;
+mod timeline;
 
+pub use error::*;
 pub use mapper::*;
+pub use keymap::*;
+pub use layout::*;
+pub use text::*;
+pub use format::*;
+pub use file::*;
+pub use binary::*;
+pub use journal::*;
+pub use csv::*;
+pub use xdotool::*;
+pub use browser::*;
+pub use dsl::*;
+pub use combined::*;
+pub use diff::*;
+pub use stats::*;
+pub use heatmap::*;
+pub use shortcut::*;
+pub use gesture::*;
+pub use validate::*;
+pub use edit::*;
 pub use record::*;
 pub use display::*;
 pub use utils::*;
@@ -1,6 +1,105 @@
 use device_query::{Keycode as DqKey, MouseButton as DqButton};
 use enigo::{Key as EnigoKey, MouseButton as EnigoButton};
-use crate::MouseEventName;
+use crate::{Error, MouseEventName, PRINTABLE_KEYS};
+
+// region platform keycodes
+/// keycodes of the keys that have no exact target in Enigo, sent with `EnigoKey::Raw`,
+/// and of the physical positions of `PRINTABLE_KEYS` (see `PlaybackMode::Physical`).
+/// (virtual-key codes on windows, `kVK_*` codes on macos and X keycodes on linux)
+#[cfg(target_os = "windows")]
+#[allow(unused)]
+mod raw_code {
+    pub const L_SHIFT: u16 = 0xA0;
+    pub const R_SHIFT: u16 = 0xA1;
+    pub const L_CONTROL: u16 = 0xA2;
+    pub const R_CONTROL: u16 = 0xA3;
+    pub const L_ALT: u16 = 0xA4;
+    pub const R_ALT: u16 = 0xA5;
+    pub const NUMPAD_0: u16 = 0x60;
+    pub const NUMPAD_1: u16 = 0x61;
+    pub const NUMPAD_2: u16 = 0x62;
+    pub const NUMPAD_3: u16 = 0x63;
+    pub const NUMPAD_4: u16 = 0x64;
+    pub const NUMPAD_5: u16 = 0x65;
+    pub const NUMPAD_6: u16 = 0x66;
+    pub const NUMPAD_7: u16 = 0x67;
+    pub const NUMPAD_8: u16 = 0x68;
+    pub const NUMPAD_9: u16 = 0x69;
+    pub const NUMPAD_MULTIPLY: u16 = 0x6A;
+    pub const NUMPAD_ADD: u16 = 0x6B;
+    pub const NUMPAD_SUBTRACT: u16 = 0x6D;
+    pub const NUMPAD_DIVIDE: u16 = 0x6F;
+    /// windows takes virtual-keys only, which are those of US QWERTY and go through the layout of the machine
+    pub const POSITIONS: [u16; 47] = [
+        0xC0, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x30, 0xBD, 0xBB,
+        0x51, 0x57, 0x45, 0x52, 0x54, 0x59, 0x55, 0x49, 0x4F, 0x50, 0xDB, 0xDD, 0xDC,
+        0x41, 0x53, 0x44, 0x46, 0x47, 0x48, 0x4A, 0x4B, 0x4C, 0xBA, 0xDE,
+        0x5A, 0x58, 0x43, 0x56, 0x42, 0x4E, 0x4D, 0xBC, 0xBE, 0xBF,
+    ];
+}
+
+#[cfg(target_os = "macos")]
+#[allow(unused)]
+mod raw_code {
+    pub const L_SHIFT: u16 = 0x38;
+    pub const R_SHIFT: u16 = 0x3C;
+    pub const L_CONTROL: u16 = 0x3B;
+    pub const R_CONTROL: u16 = 0x3E;
+    pub const L_ALT: u16 = 0x3A;
+    pub const R_ALT: u16 = 0x3D;
+    pub const NUMPAD_0: u16 = 0x52;
+    pub const NUMPAD_1: u16 = 0x53;
+    pub const NUMPAD_2: u16 = 0x54;
+    pub const NUMPAD_3: u16 = 0x55;
+    pub const NUMPAD_4: u16 = 0x56;
+    pub const NUMPAD_5: u16 = 0x57;
+    pub const NUMPAD_6: u16 = 0x58;
+    pub const NUMPAD_7: u16 = 0x59;
+    pub const NUMPAD_8: u16 = 0x5B;
+    pub const NUMPAD_9: u16 = 0x5C;
+    pub const NUMPAD_MULTIPLY: u16 = 0x43;
+    pub const NUMPAD_ADD: u16 = 0x45;
+    pub const NUMPAD_SUBTRACT: u16 = 0x4E;
+    pub const NUMPAD_DIVIDE: u16 = 0x4B;
+    pub const POSITIONS: [u16; 47] = [
+        0x32, 0x12, 0x13, 0x14, 0x15, 0x17, 0x16, 0x1A, 0x1C, 0x19, 0x1D, 0x1B, 0x18,
+        0x0C, 0x0D, 0x0E, 0x0F, 0x11, 0x10, 0x20, 0x22, 0x1F, 0x23, 0x21, 0x1E, 0x2A,
+        0x00, 0x01, 0x02, 0x03, 0x05, 0x04, 0x26, 0x28, 0x25, 0x29, 0x27,
+        0x06, 0x07, 0x08, 0x09, 0x0B, 0x2D, 0x2E, 0x2B, 0x2F, 0x2C,
+    ];
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+#[allow(unused)]
+mod raw_code {
+    pub const L_SHIFT: u16 = 50;
+    pub const R_SHIFT: u16 = 62;
+    pub const L_CONTROL: u16 = 37;
+    pub const R_CONTROL: u16 = 105;
+    pub const L_ALT: u16 = 64;
+    pub const R_ALT: u16 = 108;
+    pub const NUMPAD_0: u16 = 90;
+    pub const NUMPAD_1: u16 = 87;
+    pub const NUMPAD_2: u16 = 88;
+    pub const NUMPAD_3: u16 = 89;
+    pub const NUMPAD_4: u16 = 83;
+    pub const NUMPAD_5: u16 = 84;
+    pub const NUMPAD_6: u16 = 85;
+    pub const NUMPAD_7: u16 = 79;
+    pub const NUMPAD_8: u16 = 80;
+    pub const NUMPAD_9: u16 = 81;
+    pub const NUMPAD_MULTIPLY: u16 = 63;
+    pub const NUMPAD_ADD: u16 = 86;
+    pub const NUMPAD_SUBTRACT: u16 = 82;
+    pub const NUMPAD_DIVIDE: u16 = 106;
+    pub const POSITIONS: [u16; 47] = [
+        49, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21,
+        24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 51,
+        38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48,
+        52, 53, 54, 55, 56, 57, 58, 59, 60, 61,
+    ];
+}
+// endregion
 
 // region keyboard mapper
 pub struct KeyboardMapper {}
@@ -65,9 +164,13 @@ impl KeyboardMapper {
             DqKey::Escape => Some(EnigoKey::Escape),
             DqKey::Tab => Some(EnigoKey::Tab),
             DqKey::CapsLock => Some(EnigoKey::CapsLock),
-            DqKey::LShift | DqKey::RShift => Some(EnigoKey::Shift),
-            DqKey::LControl | DqKey::RControl => Some(EnigoKey::Control),
-            DqKey::LAlt | DqKey::RAlt => Some(EnigoKey::Alt),
+            DqKey::LShift => Some(EnigoKey::Raw(raw_code::L_SHIFT)),
+            DqKey::RShift => Some(EnigoKey::Raw(raw_code::R_SHIFT)),
+            DqKey::LControl => Some(EnigoKey::Raw(raw_code::L_CONTROL)),
+            DqKey::RControl => Some(EnigoKey::Raw(raw_code::R_CONTROL)),
+            DqKey::LAlt => Some(EnigoKey::Raw(raw_code::L_ALT)),
+            DqKey::RAlt => Some(EnigoKey::Raw(raw_code::R_ALT)),
+            DqKey::Meta => Some(EnigoKey::Meta),
             DqKey::Space => Some(EnigoKey::Space),
             DqKey::Up => Some(EnigoKey::UpArrow),
             DqKey::Right => Some(EnigoKey::RightArrow),
@@ -83,7 +186,7 @@ impl KeyboardMapper {
             DqKey::End => Some(EnigoKey::End),
             // belows have passed the simulate test
             DqKey::Grave => Some(EnigoKey::Layout('`')),
-            DqKey::Minus | DqKey::NumpadSubtract => Some(EnigoKey::Layout('-')),
+            DqKey::Minus => Some(EnigoKey::Layout('-')),
             DqKey::Equal => Some(EnigoKey::Layout('=')),
             DqKey::LeftBracket => Some(EnigoKey::Layout('[')),
             DqKey::RightBracket => Some(EnigoKey::Layout(']')),
@@ -91,9 +194,43 @@ impl KeyboardMapper {
             DqKey::Dot => Some(EnigoKey::Layout('.')),
             DqKey::Semicolon => Some(EnigoKey::Layout(';')),
             DqKey::Apostrophe => Some(EnigoKey::Layout('\'')),
-            DqKey::Slash | DqKey::NumpadDivide => Some(EnigoKey::Layout('/')),
+            DqKey::Slash => Some(EnigoKey::Layout('/')),
             DqKey::BackSlash => Some(EnigoKey::Layout('\\')),
-            // belows have no exact target in Enigo but can also use in typing
+            // belows have no exact target in Enigo, send the platform keycodes
+            DqKey::Numpad0 => Some(EnigoKey::Raw(raw_code::NUMPAD_0)),
+            DqKey::Numpad1 => Some(EnigoKey::Raw(raw_code::NUMPAD_1)),
+            DqKey::Numpad2 => Some(EnigoKey::Raw(raw_code::NUMPAD_2)),
+            DqKey::Numpad3 => Some(EnigoKey::Raw(raw_code::NUMPAD_3)),
+            DqKey::Numpad4 => Some(EnigoKey::Raw(raw_code::NUMPAD_4)),
+            DqKey::Numpad5 => Some(EnigoKey::Raw(raw_code::NUMPAD_5)),
+            DqKey::Numpad6 => Some(EnigoKey::Raw(raw_code::NUMPAD_6)),
+            DqKey::Numpad7 => Some(EnigoKey::Raw(raw_code::NUMPAD_7)),
+            DqKey::Numpad8 => Some(EnigoKey::Raw(raw_code::NUMPAD_8)),
+            DqKey::Numpad9 => Some(EnigoKey::Raw(raw_code::NUMPAD_9)),
+            DqKey::NumpadAdd => Some(EnigoKey::Raw(raw_code::NUMPAD_ADD)),
+            DqKey::NumpadSubtract => Some(EnigoKey::Raw(raw_code::NUMPAD_SUBTRACT)),
+            DqKey::NumpadMultiply => Some(EnigoKey::Raw(raw_code::NUMPAD_MULTIPLY)),
+            DqKey::NumpadDivide => Some(EnigoKey::Raw(raw_code::NUMPAD_DIVIDE)),
+            _ => None
+        }
+    }
+
+    /// `Keycode` in `device_query` => the raw code of its physical position (see `PlaybackMode::Physical`),
+    /// `None` for the keys out of the layout tables.
+    pub fn dq_to_position(key_in_dq: DqKey) -> Option<EnigoKey> {
+        PRINTABLE_KEYS.iter()
+            .position(|key| *key == key_in_dq)
+            .map(|index| EnigoKey::Raw(raw_code::POSITIONS[index]))
+    }
+
+    /// `Keycode` in `device_query` => `Key` in `Enigo`, compatible mode.
+    /// left/right modifiers are collapsed into `Shift`/`Control`/`Alt`
+    /// and numpad keys are typed as the characters they represent (the behaviour of earlier versions).
+    pub fn dq_to_enigo_compatible(key_in_dq: DqKey) -> Option<EnigoKey> {
+        match key_in_dq {
+            DqKey::LShift | DqKey::RShift => Some(EnigoKey::Shift),
+            DqKey::LControl | DqKey::RControl => Some(EnigoKey::Control),
+            DqKey::LAlt | DqKey::RAlt => Some(EnigoKey::Alt),
             DqKey::Numpad0 => Some(EnigoKey::Layout('0')),
             DqKey::Numpad1 => Some(EnigoKey::Layout('1')),
             DqKey::Numpad2 => Some(EnigoKey::Layout('2')),
@@ -104,7 +241,10 @@ impl KeyboardMapper {
             DqKey::Numpad7 => Some(EnigoKey::Layout('7')),
             DqKey::Numpad8 => Some(EnigoKey::Layout('8')),
             DqKey::Numpad9 => Some(EnigoKey::Layout('9')),
-            _ => None
+            DqKey::NumpadSubtract => Some(EnigoKey::Layout('-')),
+            DqKey::NumpadDivide => Some(EnigoKey::Layout('/')),
+            DqKey::NumpadAdd | DqKey::NumpadMultiply => None,
+            _ => KeyboardMapper::dq_to_enigo(key_in_dq)
         }
     }
 
@@ -166,9 +306,13 @@ impl KeyboardMapper {
             EnigoKey::Escape => Some(DqKey::Escape),
             EnigoKey::Tab => Some(DqKey::Tab),
             EnigoKey::CapsLock => Some(DqKey::CapsLock),
-            EnigoKey::Shift => Some(DqKey::LShift),
-            EnigoKey::Control => Some(DqKey::LControl),
-            EnigoKey::Alt => Some(DqKey::LAlt),
+            EnigoKey::Shift | EnigoKey::Raw(raw_code::L_SHIFT) => Some(DqKey::LShift),
+            EnigoKey::Raw(raw_code::R_SHIFT) => Some(DqKey::RShift),
+            EnigoKey::Control | EnigoKey::Raw(raw_code::L_CONTROL) => Some(DqKey::LControl),
+            EnigoKey::Raw(raw_code::R_CONTROL) => Some(DqKey::RControl),
+            EnigoKey::Alt | EnigoKey::Raw(raw_code::L_ALT) => Some(DqKey::LAlt),
+            EnigoKey::Raw(raw_code::R_ALT) => Some(DqKey::RAlt),
+            EnigoKey::Meta => Some(DqKey::Meta),
             EnigoKey::Space => Some(DqKey::Space),
             EnigoKey::UpArrow => Some(DqKey::Up),
             EnigoKey::RightArrow => Some(DqKey::Right),
@@ -194,6 +338,21 @@ impl KeyboardMapper {
             EnigoKey::Layout('\'') => Some(DqKey::Apostrophe),
             EnigoKey::Layout('/') => Some(DqKey::Slash),
             EnigoKey::Layout('\\') => Some(DqKey::BackSlash),
+            // numpad
+            EnigoKey::Raw(raw_code::NUMPAD_0) => Some(DqKey::Numpad0),
+            EnigoKey::Raw(raw_code::NUMPAD_1) => Some(DqKey::Numpad1),
+            EnigoKey::Raw(raw_code::NUMPAD_2) => Some(DqKey::Numpad2),
+            EnigoKey::Raw(raw_code::NUMPAD_3) => Some(DqKey::Numpad3),
+            EnigoKey::Raw(raw_code::NUMPAD_4) => Some(DqKey::Numpad4),
+            EnigoKey::Raw(raw_code::NUMPAD_5) => Some(DqKey::Numpad5),
+            EnigoKey::Raw(raw_code::NUMPAD_6) => Some(DqKey::Numpad6),
+            EnigoKey::Raw(raw_code::NUMPAD_7) => Some(DqKey::Numpad7),
+            EnigoKey::Raw(raw_code::NUMPAD_8) => Some(DqKey::Numpad8),
+            EnigoKey::Raw(raw_code::NUMPAD_9) => Some(DqKey::Numpad9),
+            EnigoKey::Raw(raw_code::NUMPAD_ADD) => Some(DqKey::NumpadAdd),
+            EnigoKey::Raw(raw_code::NUMPAD_SUBTRACT) => Some(DqKey::NumpadSubtract),
+            EnigoKey::Raw(raw_code::NUMPAD_MULTIPLY) => Some(DqKey::NumpadMultiply),
+            EnigoKey::Raw(raw_code::NUMPAD_DIVIDE) => Some(DqKey::NumpadDivide),
             _ => None
         }
     }
@@ -262,6 +421,7 @@ impl KeyboardMapper {
             DqKey::RControl => Some("ControlRight"),
             DqKey::LAlt => Some("AltLeft"),
             DqKey::RAlt => Some("AltRight"),
+            DqKey::Meta => Some("MetaLeft"),
             DqKey::Space => Some("Space"),
             DqKey::Up => Some("ArrowUp"),
             DqKey::Right => Some("ArrowRight"),
@@ -269,7 +429,7 @@ impl KeyboardMapper {
             DqKey::Left => Some("ArrowLeft"),
             DqKey::Enter => Some("Enter"),
             DqKey::Backspace => Some("Backspace"),
-            // DqKey::Insert => None,
+            DqKey::Insert => Some("Insert"),
             DqKey::Delete => Some("Delete"),
             DqKey::Home => Some("Home"),
             DqKey::PageUp => Some("PageUp"),
@@ -306,6 +466,11 @@ impl KeyboardMapper {
         }
     }
 
+    /// `dq_to_front` but fails with `Error::UnknownKey` (the name in `device_query`) for a key without `code`
+    pub fn dq_to_front_checked(key_in_dq: DqKey) -> Result<&'static str, Error> {
+        KeyboardMapper::dq_to_front(key_in_dq).ok_or_else(|| Error::UnknownKey(format!("{:?}", key_in_dq)))
+    }
+
     /// `code` in frontend => `Keycode` in `device_query`
     pub fn front_to_dq(code_in_front: &str) -> Option<DqKey> {
         match code_in_front {
@@ -370,6 +535,7 @@ impl KeyboardMapper {
             "ControlRight" => Some(DqKey::RControl),
             "AltLeft" => Some(DqKey::LAlt),
             "AltRight" => Some(DqKey::RAlt),
+            "MetaLeft" => Some(DqKey::Meta),
             "Space" => Some(DqKey::Space),
             "ArrowUp" => Some(DqKey::Up),
             "ArrowRight" => Some(DqKey::Right),
@@ -377,7 +543,7 @@ impl KeyboardMapper {
             "ArrowLeft" => Some(DqKey::Left),
             "Enter" => Some(DqKey::Enter),
             "Backspace" => Some(DqKey::Backspace),
-            // DqKey::Insert => None,
+            "Insert" => Some(DqKey::Insert),
             "Delete" => Some(DqKey::Delete),
             "Home" => Some(DqKey::Home),
             "PageUp" => Some(DqKey::PageUp),
@@ -414,6 +580,222 @@ impl KeyboardMapper {
         }
     }
 
+    /// `Keycode` in `device_query` => keysym name in X11 (as `xdotool` takes)
+    pub fn dq_to_keysym(key_in_dq: DqKey) -> Option<&'static str> {
+        match key_in_dq {
+            // F1-F12
+            DqKey::F1 => Some("F1"),
+            DqKey::F2 => Some("F2"),
+            DqKey::F3 => Some("F3"),
+            DqKey::F4 => Some("F4"),
+            DqKey::F5 => Some("F5"),
+            DqKey::F6 => Some("F6"),
+            DqKey::F7 => Some("F7"),
+            DqKey::F8 => Some("F8"),
+            DqKey::F9 => Some("F9"),
+            DqKey::F10 => Some("F10"),
+            DqKey::F11 => Some("F11"),
+            DqKey::F12 => Some("F12"),
+            // 0-9
+            DqKey::Key0 => Some("0"),
+            DqKey::Key1 => Some("1"),
+            DqKey::Key2 => Some("2"),
+            DqKey::Key3 => Some("3"),
+            DqKey::Key4 => Some("4"),
+            DqKey::Key5 => Some("5"),
+            DqKey::Key6 => Some("6"),
+            DqKey::Key7 => Some("7"),
+            DqKey::Key8 => Some("8"),
+            DqKey::Key9 => Some("9"),
+            // A-Z
+            DqKey::A => Some("a"),
+            DqKey::B => Some("b"),
+            DqKey::C => Some("c"),
+            DqKey::D => Some("d"),
+            DqKey::E => Some("e"),
+            DqKey::F => Some("f"),
+            DqKey::G => Some("g"),
+            DqKey::H => Some("h"),
+            DqKey::I => Some("i"),
+            DqKey::J => Some("j"),
+            DqKey::K => Some("k"),
+            DqKey::L => Some("l"),
+            DqKey::M => Some("m"),
+            DqKey::N => Some("n"),
+            DqKey::O => Some("o"),
+            DqKey::P => Some("p"),
+            DqKey::Q => Some("q"),
+            DqKey::R => Some("r"),
+            DqKey::S => Some("s"),
+            DqKey::T => Some("t"),
+            DqKey::U => Some("u"),
+            DqKey::V => Some("v"),
+            DqKey::W => Some("w"),
+            DqKey::X => Some("x"),
+            DqKey::Y => Some("y"),
+            DqKey::Z => Some("z"),
+            // from left to right, from top to bottom
+            DqKey::Escape => Some("Escape"),
+            DqKey::Tab => Some("Tab"),
+            DqKey::CapsLock => Some("Caps_Lock"),
+            DqKey::LShift => Some("Shift_L"),
+            DqKey::RShift => Some("Shift_R"),
+            DqKey::LControl => Some("Control_L"),
+            DqKey::RControl => Some("Control_R"),
+            DqKey::LAlt => Some("Alt_L"),
+            DqKey::RAlt => Some("Alt_R"),
+            DqKey::Meta => Some("Super_L"),
+            DqKey::Space => Some("space"),
+            DqKey::Up => Some("Up"),
+            DqKey::Right => Some("Right"),
+            DqKey::Down => Some("Down"),
+            DqKey::Left => Some("Left"),
+            DqKey::Enter => Some("Return"),
+            DqKey::Backspace => Some("BackSpace"),
+            DqKey::Insert => Some("Insert"),
+            DqKey::Delete => Some("Delete"),
+            DqKey::Home => Some("Home"),
+            DqKey::PageUp => Some("Prior"),
+            DqKey::PageDown => Some("Next"),
+            DqKey::End => Some("End"),
+            DqKey::Grave => Some("grave"),
+            DqKey::Minus => Some("minus"),
+            DqKey::Equal => Some("equal"),
+            DqKey::LeftBracket => Some("bracketleft"),
+            DqKey::RightBracket => Some("bracketright"),
+            DqKey::Comma => Some("comma"),
+            DqKey::Dot => Some("period"),
+            DqKey::Semicolon => Some("semicolon"),
+            DqKey::Apostrophe => Some("apostrophe"),
+            DqKey::Slash => Some("slash"),
+            DqKey::BackSlash => Some("backslash"),
+            // numpad
+            DqKey::Numpad0 => Some("KP_0"),
+            DqKey::Numpad1 => Some("KP_1"),
+            DqKey::Numpad2 => Some("KP_2"),
+            DqKey::Numpad3 => Some("KP_3"),
+            DqKey::Numpad4 => Some("KP_4"),
+            DqKey::Numpad5 => Some("KP_5"),
+            DqKey::Numpad6 => Some("KP_6"),
+            DqKey::Numpad7 => Some("KP_7"),
+            DqKey::Numpad8 => Some("KP_8"),
+            DqKey::Numpad9 => Some("KP_9"),
+            DqKey::NumpadAdd => Some("KP_Add"),
+            DqKey::NumpadSubtract => Some("KP_Subtract"),
+            DqKey::NumpadMultiply => Some("KP_Multiply"),
+            DqKey::NumpadDivide => Some("KP_Divide"),
+            _ => None
+        }
+    }
+
+    /// keysym name in X11 => `Keycode` in `device_query`
+    pub fn keysym_to_dq(keysym: &str) -> Option<DqKey> {
+        match keysym {
+            // F1-F12
+            "F1" => Some(DqKey::F1),
+            "F2" => Some(DqKey::F2),
+            "F3" => Some(DqKey::F3),
+            "F4" => Some(DqKey::F4),
+            "F5" => Some(DqKey::F5),
+            "F6" => Some(DqKey::F6),
+            "F7" => Some(DqKey::F7),
+            "F8" => Some(DqKey::F8),
+            "F9" => Some(DqKey::F9),
+            "F10" => Some(DqKey::F10),
+            "F11" => Some(DqKey::F11),
+            "F12" => Some(DqKey::F12),
+            // 0-9
+            "0" => Some(DqKey::Key0),
+            "1" => Some(DqKey::Key1),
+            "2" => Some(DqKey::Key2),
+            "3" => Some(DqKey::Key3),
+            "4" => Some(DqKey::Key4),
+            "5" => Some(DqKey::Key5),
+            "6" => Some(DqKey::Key6),
+            "7" => Some(DqKey::Key7),
+            "8" => Some(DqKey::Key8),
+            "9" => Some(DqKey::Key9),
+            // A-Z
+            "a" => Some(DqKey::A),
+            "b" => Some(DqKey::B),
+            "c" => Some(DqKey::C),
+            "d" => Some(DqKey::D),
+            "e" => Some(DqKey::E),
+            "f" => Some(DqKey::F),
+            "g" => Some(DqKey::G),
+            "h" => Some(DqKey::H),
+            "i" => Some(DqKey::I),
+            "j" => Some(DqKey::J),
+            "k" => Some(DqKey::K),
+            "l" => Some(DqKey::L),
+            "m" => Some(DqKey::M),
+            "n" => Some(DqKey::N),
+            "o" => Some(DqKey::O),
+            "p" => Some(DqKey::P),
+            "q" => Some(DqKey::Q),
+            "r" => Some(DqKey::R),
+            "s" => Some(DqKey::S),
+            "t" => Some(DqKey::T),
+            "u" => Some(DqKey::U),
+            "v" => Some(DqKey::V),
+            "w" => Some(DqKey::W),
+            "x" => Some(DqKey::X),
+            "y" => Some(DqKey::Y),
+            "z" => Some(DqKey::Z),
+            // from left to right, from top to bottom
+            "Escape" => Some(DqKey::Escape),
+            "Tab" => Some(DqKey::Tab),
+            "Caps_Lock" => Some(DqKey::CapsLock),
+            "Shift_L" => Some(DqKey::LShift),
+            "Shift_R" => Some(DqKey::RShift),
+            "Control_L" => Some(DqKey::LControl),
+            "Control_R" => Some(DqKey::RControl),
+            "Alt_L" => Some(DqKey::LAlt),
+            "Alt_R" => Some(DqKey::RAlt),
+            "Super_L" => Some(DqKey::Meta),
+            "space" => Some(DqKey::Space),
+            "Up" => Some(DqKey::Up),
+            "Right" => Some(DqKey::Right),
+            "Down" => Some(DqKey::Down),
+            "Left" => Some(DqKey::Left),
+            "Return" => Some(DqKey::Enter),
+            "BackSpace" => Some(DqKey::Backspace),
+            "Insert" => Some(DqKey::Insert),
+            "Delete" => Some(DqKey::Delete),
+            "Home" => Some(DqKey::Home),
+            "Prior" => Some(DqKey::PageUp),
+            "Next" => Some(DqKey::PageDown),
+            "End" => Some(DqKey::End),
+            "grave" => Some(DqKey::Grave),
+            "minus" => Some(DqKey::Minus),
+            "equal" => Some(DqKey::Equal),
+            "bracketleft" => Some(DqKey::LeftBracket),
+            "bracketright" => Some(DqKey::RightBracket),
+            "comma" => Some(DqKey::Comma),
+            "period" => Some(DqKey::Dot),
+            "semicolon" => Some(DqKey::Semicolon),
+            "apostrophe" => Some(DqKey::Apostrophe),
+            "slash" => Some(DqKey::Slash),
+            "backslash" => Some(DqKey::BackSlash),
+            // numpad
+            "KP_0" => Some(DqKey::Numpad0),
+            "KP_1" => Some(DqKey::Numpad1),
+            "KP_2" => Some(DqKey::Numpad2),
+            "KP_3" => Some(DqKey::Numpad3),
+            "KP_4" => Some(DqKey::Numpad4),
+            "KP_5" => Some(DqKey::Numpad5),
+            "KP_6" => Some(DqKey::Numpad6),
+            "KP_7" => Some(DqKey::Numpad7),
+            "KP_8" => Some(DqKey::Numpad8),
+            "KP_9" => Some(DqKey::Numpad9),
+            "KP_Add" => Some(DqKey::NumpadAdd),
+            "KP_Subtract" => Some(DqKey::NumpadSubtract),
+            "KP_Multiply" => Some(DqKey::NumpadMultiply),
+            "KP_Divide" => Some(DqKey::NumpadDivide),
+            _ => None
+        }
+    }
+
     /// `Key` in `Enigo` => `code` in frontend
     pub fn enigo_to_front(key_in_enigo: EnigoKey) -> Option<&'static str> {
         match key_in_enigo {
@@ -472,9 +854,13 @@ impl KeyboardMapper {
             EnigoKey::Escape => Some("Escape"),
             EnigoKey::Tab => Some("Tab"),
             EnigoKey::CapsLock => Some("CapsLock"),
-            EnigoKey::Shift => Some("ShiftLeft"),
-            EnigoKey::Control => Some("ControlLeft"),
-            EnigoKey::Alt => Some("AltLeft"),
+            EnigoKey::Shift | EnigoKey::Raw(raw_code::L_SHIFT) => Some("ShiftLeft"),
+            EnigoKey::Raw(raw_code::R_SHIFT) => Some("ShiftRight"),
+            EnigoKey::Control | EnigoKey::Raw(raw_code::L_CONTROL) => Some("ControlLeft"),
+            EnigoKey::Raw(raw_code::R_CONTROL) => Some("ControlRight"),
+            EnigoKey::Alt | EnigoKey::Raw(raw_code::L_ALT) => Some("AltLeft"),
+            EnigoKey::Raw(raw_code::R_ALT) => Some("AltRight"),
+            EnigoKey::Meta => Some("MetaLeft"),
             EnigoKey::Space => Some("Space"),
             EnigoKey::UpArrow => Some("ArrowUp"),
             EnigoKey::RightArrow => Some("ArrowRight"),
@@ -500,6 +886,21 @@ impl KeyboardMapper {
             EnigoKey::Layout('\'') => Some("Quote"),
             EnigoKey::Layout('/') => Some("Slash"),
             EnigoKey::Layout('\\') => Some("BackSlash"),
+            // numpad
+            EnigoKey::Raw(raw_code::NUMPAD_0) => Some("Numpad0"),
+            EnigoKey::Raw(raw_code::NUMPAD_1) => Some("Numpad1"),
+            EnigoKey::Raw(raw_code::NUMPAD_2) => Some("Numpad2"),
+            EnigoKey::Raw(raw_code::NUMPAD_3) => Some("Numpad3"),
+            EnigoKey::Raw(raw_code::NUMPAD_4) => Some("Numpad4"),
+            EnigoKey::Raw(raw_code::NUMPAD_5) => Some("Numpad5"),
+            EnigoKey::Raw(raw_code::NUMPAD_6) => Some("Numpad6"),
+            EnigoKey::Raw(raw_code::NUMPAD_7) => Some("Numpad7"),
+            EnigoKey::Raw(raw_code::NUMPAD_8) => Some("Numpad8"),
+            EnigoKey::Raw(raw_code::NUMPAD_9) => Some("Numpad9"),
+            EnigoKey::Raw(raw_code::NUMPAD_ADD) => Some("NumpadAdd"),
+            EnigoKey::Raw(raw_code::NUMPAD_SUBTRACT) => Some("NumpadSubtract"),
+            EnigoKey::Raw(raw_code::NUMPAD_MULTIPLY) => Some("NumpadMultiply"),
+            EnigoKey::Raw(raw_code::NUMPAD_DIVIDE) => Some("NumpadDivide"),
             _ => None
         }
     }
@@ -521,16 +922,16 @@ impl KeyboardMapper {
             "F11" => Some(EnigoKey::F11),
             "F12" => Some(EnigoKey::F12),
             // 0-9
-            "Digit0" | "Numpad0" => Some(EnigoKey::Layout('0')),
-            "Digit1" | "Numpad1" => Some(EnigoKey::Layout('1')),
-            "Digit2" | "Numpad2" => Some(EnigoKey::Layout('2')),
-            "Digit3" | "Numpad3" => Some(EnigoKey::Layout('3')),
-            "Digit4" | "Numpad4" => Some(EnigoKey::Layout('4')),
-            "Digit5" | "Numpad5" => Some(EnigoKey::Layout('5')),
-            "Digit6" | "Numpad6" => Some(EnigoKey::Layout('6')),
-            "Digit7" | "Numpad7" => Some(EnigoKey::Layout('7')),
-            "Digit8" | "Numpad8" => Some(EnigoKey::Layout('8')),
-            "Digit9" | "Numpad9" => Some(EnigoKey::Layout('9')),
+            "Digit0" => Some(EnigoKey::Layout('0')),
+            "Digit1" => Some(EnigoKey::Layout('1')),
+            "Digit2" => Some(EnigoKey::Layout('2')),
+            "Digit3" => Some(EnigoKey::Layout('3')),
+            "Digit4" => Some(EnigoKey::Layout('4')),
+            "Digit5" => Some(EnigoKey::Layout('5')),
+            "Digit6" => Some(EnigoKey::Layout('6')),
+            "Digit7" => Some(EnigoKey::Layout('7')),
+            "Digit8" => Some(EnigoKey::Layout('8')),
+            "Digit9" => Some(EnigoKey::Layout('9')),
             // A-Z
             "KeyA" => Some(EnigoKey::Layout('a')),
             "KeyB" => Some(EnigoKey::Layout('b')),
@@ -562,9 +963,13 @@ impl KeyboardMapper {
             "Escape" => Some(EnigoKey::Escape),
             "Tab" => Some(EnigoKey::Tab),
             "CapsLock" => Some(EnigoKey::CapsLock),
-            "ShiftLeft" | "ShiftRight" => Some(EnigoKey::Shift),
-            "ControlLeft" | "ControlRight" => Some(EnigoKey::Control),
-            "AltLeft" | "AltRight" => Some(EnigoKey::Alt),
+            "ShiftLeft" => Some(EnigoKey::Raw(raw_code::L_SHIFT)),
+            "ShiftRight" => Some(EnigoKey::Raw(raw_code::R_SHIFT)),
+            "ControlLeft" => Some(EnigoKey::Raw(raw_code::L_CONTROL)),
+            "ControlRight" => Some(EnigoKey::Raw(raw_code::R_CONTROL)),
+            "AltLeft" => Some(EnigoKey::Raw(raw_code::L_ALT)),
+            "AltRight" => Some(EnigoKey::Raw(raw_code::R_ALT)),
+            "MetaLeft" => Some(EnigoKey::Meta),
             "Space" => Some(EnigoKey::Space),
             "ArrowUp" => Some(EnigoKey::UpArrow),
             "ArrowRight" => Some(EnigoKey::RightArrow),
@@ -580,7 +985,7 @@ impl KeyboardMapper {
             "End" => Some(EnigoKey::End),
             // belows have passed the simulate test
             "Backquote" => Some(EnigoKey::Layout('`')),
-            "Minus" | "NumpadSubtract" => Some(EnigoKey::Layout('-')),
+            "Minus" => Some(EnigoKey::Layout('-')),
             "Equal" => Some(EnigoKey::Layout('=')),
             "BracketLeft" => Some(EnigoKey::Layout('[')),
             "BracketRight" => Some(EnigoKey::Layout(']')),
@@ -588,8 +993,23 @@ impl KeyboardMapper {
             "Period" => Some(EnigoKey::Layout('.')),
             "Semicolon" => Some(EnigoKey::Layout(';')),
             "Quote" => Some(EnigoKey::Layout('\'')),
-            "Slash" | "NumpadDivide" => Some(EnigoKey::Layout('/')),
+            "Slash" => Some(EnigoKey::Layout('/')),
             "BackSlash" => Some(EnigoKey::Layout('\\')),
+            // numpad
+            "Numpad0" => Some(EnigoKey::Raw(raw_code::NUMPAD_0)),
+            "Numpad1" => Some(EnigoKey::Raw(raw_code::NUMPAD_1)),
+            "Numpad2" => Some(EnigoKey::Raw(raw_code::NUMPAD_2)),
+            "Numpad3" => Some(EnigoKey::Raw(raw_code::NUMPAD_3)),
+            "Numpad4" => Some(EnigoKey::Raw(raw_code::NUMPAD_4)),
+            "Numpad5" => Some(EnigoKey::Raw(raw_code::NUMPAD_5)),
+            "Numpad6" => Some(EnigoKey::Raw(raw_code::NUMPAD_6)),
+            "Numpad7" => Some(EnigoKey::Raw(raw_code::NUMPAD_7)),
+            "Numpad8" => Some(EnigoKey::Raw(raw_code::NUMPAD_8)),
+            "Numpad9" => Some(EnigoKey::Raw(raw_code::NUMPAD_9)),
+            "NumpadAdd" => Some(EnigoKey::Raw(raw_code::NUMPAD_ADD)),
+            "NumpadSubtract" => Some(EnigoKey::Raw(raw_code::NUMPAD_SUBTRACT)),
+            "NumpadMultiply" => Some(EnigoKey::Raw(raw_code::NUMPAD_MULTIPLY)),
+            "NumpadDivide" => Some(EnigoKey::Raw(raw_code::NUMPAD_DIVIDE)),
             _ => None
         }
     }
@@ -606,13 +1026,83 @@ impl MouseMapper {
         match ev_name {
             MouseEventName::LeftDown => (EnigoButton::Left, true),
             MouseEventName::LeftUp => (EnigoButton::Left, false),
-            MouseEventName::RightDown => (EnigoButton::Right, false),
+            MouseEventName::RightDown => (EnigoButton::Right, true),
             MouseEventName::RightUp => (EnigoButton::Right, false),
-            MouseEventName::MidDown => (EnigoButton::Middle, false),
+            MouseEventName::MidDown => (EnigoButton::Middle, true),
             MouseEventName::MidUp => (EnigoButton::Middle, false),
         }
     }
 
+    /// `ev_name` in serialized action => custom enum `MouseEventName`
+    pub fn number_to_ev_name(number: usize) -> Result<MouseEventName, Error> {
+        match number {
+            1 => Ok(MouseEventName::LeftDown),
+            2 => Ok(MouseEventName::LeftUp),
+            3 => Ok(MouseEventName::RightDown),
+            4 => Ok(MouseEventName::RightUp),
+            5 => Ok(MouseEventName::MidDown),
+            6 => Ok(MouseEventName::MidUp),
+            _ => Err(Error::UnknownMouseEvent(number))
+        }
+    }
+
+    /// custom enum `MouseEventName` => `ev_name` in serialized action
+    pub fn ev_name_to_number(ev_name: MouseEventName) -> usize {
+        match ev_name {
+            MouseEventName::LeftDown => 1,
+            MouseEventName::LeftUp => 2,
+            MouseEventName::RightDown => 3,
+            MouseEventName::RightUp => 4,
+            MouseEventName::MidDown => 5,
+            MouseEventName::MidUp => 6,
+        }
+    }
+
+    /// custom enum `MouseEventName` => `(MouseButton in device_query, is_press)`
+    pub fn ev_name_to_dq(ev_name: MouseEventName) -> (DqButton, bool) {
+        match ev_name {
+            MouseEventName::LeftDown => (1, true),
+            MouseEventName::LeftUp => (1, false),
+            MouseEventName::RightDown => (2, true),
+            MouseEventName::RightUp => (2, false),
+            MouseEventName::MidDown => (3, true),
+            MouseEventName::MidUp => (3, false),
+        }
+    }
+
+    /// `(MouseButton in device_query, is_press)` => custom enum `MouseEventName`
+    pub fn dq_to_ev_name(button_in_dq: DqButton, press: bool) -> Option<MouseEventName> {
+        match (button_in_dq, press) {
+            (1, true) => Some(MouseEventName::LeftDown),
+            (1, false) => Some(MouseEventName::LeftUp),
+            (2, true) => Some(MouseEventName::RightDown),
+            (2, false) => Some(MouseEventName::RightUp),
+            (3, true) => Some(MouseEventName::MidDown),
+            (3, false) => Some(MouseEventName::MidUp),
+            _ => None
+        }
+    }
+
+    /// `MouseButton` in `device_query` => button number in X11 (1: left, 2: middle, 3: right)
+    pub fn dq_to_x(button_in_dq: DqButton) -> Option<usize> {
+        match button_in_dq {
+            1 => Some(1),
+            2 => Some(3),
+            3 => Some(2),
+            _ => None
+        }
+    }
+
+    /// button number in X11 => `MouseButton` in `device_query`
+    pub fn x_to_dq(button_in_x: usize) -> Option<DqButton> {
+        match button_in_x {
+            1 => Some(1),
+            3 => Some(2),
+            2 => Some(3),
+            _ => None
+        }
+    }
+
     /// `MouseButton` in `device_query` => `MouseButton` in `Enigo`
     pub fn dq_to_enigo(button_in_dq: DqButton) -> Option<EnigoButton> {
         match button_in_dq {
@@ -673,4 +1163,38 @@ impl MouseMapper {
         }
     }
 }
-// endregion
\ No newline at end of file
+// endregion
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// left/right modifiers and numpad keys keep their identity through `Enigo`
+    #[test]
+    fn side_specific_keys() {
+        let keys = [
+            DqKey::LShift, DqKey::RShift, DqKey::LControl, DqKey::RControl, DqKey::LAlt, DqKey::RAlt,
+            DqKey::Numpad0, DqKey::Numpad5, DqKey::Numpad9,
+            DqKey::NumpadAdd, DqKey::NumpadSubtract, DqKey::NumpadMultiply, DqKey::NumpadDivide,
+        ];
+        for key in keys {
+            let enigo_key = KeyboardMapper::dq_to_enigo(key).unwrap();
+            assert_eq!(KeyboardMapper::enigo_to_dq(enigo_key), Some(key));
+
+            let front = KeyboardMapper::dq_to_front(key).unwrap();
+            assert_eq!(KeyboardMapper::front_to_enigo(front), Some(enigo_key));
+            assert_eq!(KeyboardMapper::enigo_to_front(enigo_key), Some(front));
+        }
+    }
+
+    /// the compatible mode collapses them as before
+    #[test]
+    fn compatible_keys() {
+        assert_eq!(KeyboardMapper::dq_to_enigo_compatible(DqKey::RShift), Some(EnigoKey::Shift));
+        assert_eq!(KeyboardMapper::dq_to_enigo_compatible(DqKey::RControl), Some(EnigoKey::Control));
+        assert_eq!(KeyboardMapper::dq_to_enigo_compatible(DqKey::LAlt), Some(EnigoKey::Alt));
+        assert_eq!(KeyboardMapper::dq_to_enigo_compatible(DqKey::Numpad1), Some(EnigoKey::Layout('1')));
+        assert_eq!(KeyboardMapper::dq_to_enigo_compatible(DqKey::A), Some(EnigoKey::Layout('a')));
+    }
+}
+// endregion
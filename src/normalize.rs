@@ -0,0 +1,120 @@
+use crate::{KeyboardAction, MouseAction};
+use crate::timeline::TimedEvent;
+
+// region normalize
+/// see `KeyboardAction.normalize`
+fn normalize_events<E: TimedEvent>(evs: &mut Vec<E>, till: &mut u64) {
+    // stable, events at the same time keep their order
+    evs.sort_by_key(|ev| ev.timestamp());
+
+    // the held keys with the index of their presses in `kept`, in the order they are pressed
+    let mut held: Vec<(E::Key, usize)> = vec![];
+    let mut kept: Vec<E> = vec![];
+    for ev in evs.drain(..) {
+        let (key, press) = ev.key();
+        let position = held.iter().position(|(held_key, _)| *held_key == key);
+        match (press, position) {
+            // auto-repeat
+            (true, Some(_)) => continue,
+            (true, None) => held.push((key, kept.len())),
+            (false, Some(position)) => {
+                held.remove(position);
+            }
+            // orphan release, e.g. of a key held when the record started
+            (false, None) => continue,
+        }
+        kept.push(ev);
+    }
+
+    // rebase to the first event
+    let offset = kept.first().map(|ev| ev.timestamp()).unwrap_or(0);
+    for ev in kept.iter_mut() {
+        ev.set_timestamp(ev.timestamp() - offset);
+    }
+    *till = till.saturating_sub(offset);
+
+    // release the keys still held at the end, the last pressed first
+    let end = kept.last().map(|ev| ev.timestamp()).unwrap_or(0).max(*till);
+    for (_, index) in held.into_iter().rev() {
+        let mut ev = kept[index].release();
+        ev.set_timestamp(end);
+        kept.push(ev);
+    }
+
+    // `till` is not before the last event
+    *till = end;
+    *evs = kept;
+}
+
+impl KeyboardAction {
+    /// Repair the action in place:
+    /// sort the events by timestamp (stable), drop the repeated presses of held keys (auto-repeat),
+    /// drop the releases without press, rebase the timestamps so that the first event is at 0,
+    /// release the keys still held at `till` and make `till` not before the last event.
+    /// `validate` finds no problem afterwards.
+    pub fn normalize(&mut self) {
+        normalize_events(&mut self.evs, &mut self.till);
+    }
+}
+
+impl MouseAction {
+    /// `KeyboardAction.normalize` for the buttons, a missing release is at the position of its press.
+    pub fn normalize(&mut self) {
+        normalize_events(&mut self.evs, &mut self.till);
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use device_query::Keycode;
+    use crate::{KeyboardEv, MouseEv, MouseEventName};
+    use super::*;
+
+    #[test]
+    fn normalize_keyboard() {
+        let mut action = KeyboardAction {
+            evs: vec![
+                // held when the record started
+                KeyboardEv { code: Keycode::LShift, press: false, timestamp: 1000 },
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 1100 },
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 1150 },
+                KeyboardEv { code: Keycode::B, press: true, timestamp: 1300 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 1200 },
+            ],
+            till: 1250,
+        };
+        action.normalize();
+
+        let evs: Vec<(Keycode, bool, u64)> = action.evs.iter().map(|ev| (ev.code, ev.press, ev.timestamp)).collect();
+        assert_eq!(evs, vec![
+            (Keycode::A, true, 0),
+            (Keycode::A, false, 100),
+            (Keycode::B, true, 200),
+            (Keycode::B, false, 200),
+        ]);
+        assert_eq!(action.till, 200);
+        assert!(action.validate().is_empty());
+    }
+
+    #[test]
+    fn normalize_mouse() {
+        let mut action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::RightDown, position: (1, 2), timestamp: 50 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (3, 4), timestamp: 60 },
+            ],
+            till: 500,
+        };
+        action.normalize();
+
+        assert_eq!(action.evs.len(), 2);
+        assert!(matches!(action.evs[1].ev_name, MouseEventName::RightUp));
+        assert_eq!(action.evs[1].position, (1, 2));
+        assert_eq!(action.evs[1].timestamp, 450);
+        assert_eq!(action.till, 450);
+        assert!(action.validate().is_empty());
+    }
+}
+// endregion
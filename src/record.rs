@@ -1,9 +1,11 @@
+use std::fs::File;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use device_query::{DeviceEvents, DeviceQuery, DeviceState, Keycode};
 use serde::{Deserialize, Serialize};
-use serde_json::{from_str as json_parse, to_string as json_stringify, Result as SerdeResult};
-use crate::{KeyboardMapper};
+use serde_json::to_string as json_stringify;
+use crate::{Document, Error, JournalWriter, KeyboardMapper, KeyMap, Metadata, MouseMapper, SharedJournal, SkippedEvent, append_journal, finish_journal, parse_document, share_journal};
 
 // region keyboard event recorder
 /// single record of keyboard event
@@ -40,57 +42,132 @@ pub struct KeyboardActionSerializable {
 }
 
 impl KeyboardAction {
-    pub fn from_string(string_source: &str) -> Result<KeyboardAction, ()> {
-        let action_string_code: SerdeResult<KeyboardActionSerializable> = json_parse(string_source);
-        match action_string_code {
-            Ok(_action) => {
-                let mut action = KeyboardAction {
-                    evs: vec![],
-                    till: _action.till,
-                };
-
-                for ev in _action.evs.iter() {
-                    match KeyboardMapper::front_to_dq(&ev.code) {
-                        Some(code) => {
-                            action.evs.push(KeyboardEv {
-                                code,
-                                press: ev.press,
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        None => ()
+    pub fn from_string(string_source: &str) -> Result<KeyboardAction, Error> {
+        KeyboardAction::from_string_with_keymap(string_source, &KeyMap::new())
+    }
+
+    /// `from_string` but every code is translated by `keymap` before it is parsed.
+    pub fn from_string_with_keymap(string_source: &str, keymap: &KeyMap) -> Result<KeyboardAction, Error> {
+        let document: Document<KeyboardActionSerializable> = parse_document(string_source, "keyboard")?;
+        Ok(KeyboardAction::from_serializable(&document.action, keymap).0)
+    }
+
+    /// `from_string` but the metadata in the header is returned beside the action.
+    /// (`Metadata::default()` for the records of v0.1 which have no header)
+    pub fn from_string_with_meta(string_source: &str) -> Result<(KeyboardAction, Metadata), Error> {
+        let document: Document<KeyboardActionSerializable> = parse_document(string_source, "keyboard")?;
+        Ok((KeyboardAction::from_serializable(&document.action, &KeyMap::new()).0, document.meta))
+    }
+
+    /// `from_string` but fails with the index and the code of the first event whose `code` is unknown,
+    /// instead of leaving it out silently.
+    pub fn from_string_strict(string_source: &str) -> Result<KeyboardAction, Error> {
+        let (action, mut skipped) = KeyboardAction::from_string_lenient(string_source)?;
+        match skipped.is_empty() {
+            true => Ok(action),
+            false => Err(skipped.remove(0).into_error())
+        }
+    }
+
+    /// `from_string` but the events left out (with unknown `code`) are returned beside the action.
+    pub fn from_string_lenient(string_source: &str) -> Result<(KeyboardAction, Vec<SkippedEvent>), Error> {
+        let document: Document<KeyboardActionSerializable> = parse_document(string_source, "keyboard")?;
+        Ok(KeyboardAction::from_serializable(&document.action, &KeyMap::new()))
+    }
+
+    /// Events whose `code` is unknown (after translated by `keymap`) are left out and returned beside the action.
+    pub fn from_serializable(source: &KeyboardActionSerializable, keymap: &KeyMap) -> (KeyboardAction, Vec<SkippedEvent>) {
+        let mut action = KeyboardAction {
+            evs: vec![],
+            till: source.till,
+        };
+        let mut skipped = vec![];
+
+        for (index, ev) in source.evs.iter().enumerate() {
+            // `None` is suppressed on purpose
+            if let Some(code) = keymap.translate_code(&ev.code) {
+                match KeyboardMapper::front_to_dq(code) {
+                    Some(code) => {
+                        action.evs.push(KeyboardEv {
+                            code,
+                            press: ev.press,
+                            timestamp: ev.timestamp,
+                        })
                     }
+                    None => skipped.push(SkippedEvent { index, reason: Error::UnknownKey(ev.code.clone()) })
                 }
-
-                Ok(action)
             }
-            Err(_) => Err(())
+        }
+
+        (action, skipped)
+    }
+
+    /// Serialize to the latest document (see `FORMAT_VERSION`) with the metadata of this machine (`Metadata::of_host`).
+    /// It fails with the index and the key of the first event whose key has no `code` in frontend.
+    pub fn to_string(&self) -> Result<String, Error> {
+        self.to_string_with_meta(&Metadata::of_host())
+    }
+
+    /// `to_string` with the given metadata in the header.
+    pub fn to_string_with_meta(&self, meta: &Metadata) -> Result<String, Error> {
+        self.to_document(meta.clone(), &KeyMap::new())
+    }
+
+    /// `to_string` but every key is translated by `keymap` before it is serialized.
+    pub fn to_string_with_keymap(&self, keymap: &KeyMap) -> Result<String, Error> {
+        self.to_document(Metadata::of_host(), keymap)
+    }
+
+    fn to_document(&self, meta: Metadata, keymap: &KeyMap) -> Result<String, Error> {
+        match json_stringify(&Document::new("keyboard", meta, self.to_serializable_strict(keymap)?)) {
+            Ok(s) => Ok(s),
+            Err(err) => Err(Error::from(err))
         }
     }
 
-    pub fn to_string(&self) -> Result<String, ()> {
+    /// `to_serializable` of the writers, it fails with the index and the key of the first event whose key has no
+    /// `code` in frontend.
+    pub(crate) fn to_serializable_strict(&self, keymap: &KeyMap) -> Result<KeyboardActionSerializable, Error> {
+        let (action, mut skipped) = self.to_serializable_lenient(keymap);
+        match skipped.is_empty() {
+            true => Ok(action),
+            false => Err(skipped.remove(0).into_error())
+        }
+    }
+
+    /// Keys without `code` in frontend (after translated by `keymap`) are left out.
+    pub fn to_serializable(&self, keymap: &KeyMap) -> KeyboardActionSerializable {
+        self.to_serializable_lenient(keymap).0
+    }
+
+    /// `to_serializable` but the events left out (with a key without `code` in frontend) are returned beside.
+    /// The keys suppressed by `keymap` are left out on purpose, they are not returned.
+    pub fn to_serializable_lenient(&self, keymap: &KeyMap) -> (KeyboardActionSerializable, Vec<SkippedEvent>) {
         let mut action_string_code = KeyboardActionSerializable {
             evs: vec![],
             till: self.till,
         };
-
-        for ev in self.evs.iter() {
-            match KeyboardMapper::dq_to_front(ev.code) {
-                Some(code) => {
+        let mut skipped = vec![];
+
+        for (index, ev) in self.evs.iter().enumerate() {
+            let code = match keymap.translate(ev.code) {
+                Some(code) => code,
+                // suppressed on purpose
+                None => continue
+            };
+            match KeyboardMapper::dq_to_front_checked(code) {
+                Ok(front) => {
                     action_string_code.evs.push(KeyboardEvSerializable {
-                        code: String::from(code),
+                        code: String::from(front),
                         press: ev.press,
                         timestamp: ev.timestamp,
                     })
                 }
-                None => ()
+                Err(reason) => skipped.push(SkippedEvent { index, reason })
             }
         }
 
-        match json_stringify(&action_string_code) {
-            Ok(s) => Ok(s),
-            Err(_) => Err(())
-        }
+        (action_string_code, skipped)
     }
 }
 
@@ -100,6 +177,10 @@ pub struct KeyboardRecorder {
     /// Here, we've wrapped your vector in a Arc<Mutex<T>> so we can
     /// write to it inside our closure.
     ev_queue: Arc<Mutex<Vec<KeyboardEv>>>,
+    /// translate or suppress keys before they are recorded
+    keymap: KeyMap,
+    /// metadata of the last record
+    meta: Metadata,
 }
 
 impl KeyboardRecorder {
@@ -107,32 +188,69 @@ impl KeyboardRecorder {
         KeyboardRecorder {
             recording: Arc::new(Mutex::new(false)),
             ev_queue: Arc::new(Mutex::new(vec![])),
+            keymap: KeyMap::new(),
+            meta: Metadata::default(),
         }
     }
 
+    /// translate or suppress keys before they are recorded.
+    /// the stop key is compared before the translation.
+    pub fn set_keymap(&mut self, keymap: KeyMap) {
+        self.keymap = keymap;
+    }
+
     pub fn get_record(&self) -> Vec<KeyboardEv> {
         (*self.ev_queue.lock().unwrap()).clone()
     }
 
+    /// metadata of the last record (time, machine, stop key and cursor position at start),
+    /// to be written with `KeyboardAction.to_string_with_meta`.
+    pub fn get_metadata(&self) -> Metadata {
+        self.meta.clone()
+    }
+
     /// Doing record work in main thread.
     /// Anyway, the listener(s) is working in separator thread(s).
     /// But the guard(s) of listener(s) can only live in the scope of this function.
     /// So this call need to be 'block' until you press the key representing 'stop_code'.
     pub fn do_record(&mut self, stop_code: Keycode) -> KeyboardAction {
-        // start recording: clear records and set the signal
-        *self.ev_queue.lock().unwrap() = vec![];
-        *self.recording.lock().unwrap() = true;
+        self.record(stop_code, None).expect("a record without journal does not fail")
+    }
+
+    /// `do_record` but every event is also appended to the journal at `path` as it happens
+    /// (see `JournalWriter`), so that a crash does not lose the record.
+    /// `KeyboardAction::read_journal` rebuilds the action from the file.
+    /// It fails before recording if the journal can not be written, or when it stops with the error of the first
+    /// failed write (the journal is dropped then, but the record goes on, see `get_record`).
+    pub fn do_record_with_journal<P: AsRef<Path>>(&mut self, stop_code: Keycode, path: P) -> Result<KeyboardAction, Error> {
+        let file = File::create(path)?;
+        self.record(stop_code, Some(file))
+    }
 
+    fn record(&mut self, stop_code: Keycode, journal: Option<File>) -> Result<KeyboardAction, Error> {
         // instance
         let device_state = DeviceState::new();
-        // record start time as zero
+        self.meta = Metadata::of_record(&device_state, stop_code);
+        // record start time as zero, right after `created_at` is stamped so that records of the same session can be
+        // aligned by it (see `CombinedAction::merge_by_start`)
         let timeline = Instant::now();
+        let journal: SharedJournal = share_journal(
+            journal.map(|file| JournalWriter::new(file, "keyboard", &self.meta)).transpose()?
+        );
+
+        // start recording: clear records and set the signal
+        *self.ev_queue.lock().unwrap() = vec![];
+        *self.recording.lock().unwrap() = true;
 
         // We make a clone of the Arc<Mutex<T>> here so we can move it
         // into our closure without moving self into the closure.
         let ev_queue_down = Arc::clone(&self.ev_queue);
         let ev_queue_up = Arc::clone(&self.ev_queue);
         let recording = Arc::clone(&self.recording);
+        let keymap_down = self.keymap.clone();
+        let keymap_up = self.keymap.clone();
+        let journal_down = Arc::clone(&journal);
+        let journal_up = Arc::clone(&journal);
 
         // Note the `move` here on the closure.
         let _guard_down = device_state.on_key_down(move |key| {
@@ -143,34 +261,52 @@ impl KeyboardRecorder {
                 return;
             }
 
-            // We lock the mutex here and write to it.
-            let mut ev_queue_down = ev_queue_down.lock().unwrap();
-            ev_queue_down.push(KeyboardEv {
-                code: key.clone(),
+            let code = match keymap_down.translate(*key) {
+                Some(code) => code,
+                None => return
+            };
+
+            let ev = KeyboardEv {
+                code,
                 press: true,
                 timestamp: timeline.elapsed().as_millis() as u64,
-            })
+            };
+            append_journal(&journal_down, |journal| journal.write_keyboard_ev(&ev));
+
+            // We lock the mutex here and write to it.
+            let mut ev_queue_down = ev_queue_down.lock().unwrap();
+            ev_queue_down.push(ev)
         });
 
         // Note the `move` here on the closure.
         let _guard_up = device_state.on_key_up(move |key| {
-            // We lock the mutex here and write to it.
-            let mut ev_queue_up = ev_queue_up.lock().unwrap();
-            ev_queue_up.push(KeyboardEv {
-                code: key.clone(),
+            let code = match keymap_up.translate(*key) {
+                Some(code) => code,
+                None => return
+            };
+
+            let ev = KeyboardEv {
+                code,
                 press: false,
                 timestamp: timeline.elapsed().as_millis() as u64,
-            })
+            };
+            append_journal(&journal_up, |journal| journal.write_keyboard_ev(&ev));
+
+            // We lock the mutex here and write to it.
+            let mut ev_queue_up = ev_queue_up.lock().unwrap();
+            ev_queue_up.push(ev)
         });
 
         // a block loop till the stop key is pressed.
         loop {
             if !*self.recording.lock().unwrap() {
                 // jump out of the loop, the guard(s) will `drop` then.
-                return KeyboardAction {
+                let till = timeline.elapsed().as_millis() as u64;
+                finish_journal(&journal, till)?;
+                return Ok(KeyboardAction {
                     evs: (*self.ev_queue.lock().unwrap()).clone(),
-                    till: timeline.elapsed().as_millis() as u64,
-                };
+                    till,
+                });
             }
         }
     }
@@ -189,7 +325,7 @@ impl KeyboardRecorder {
 
 // region mouse event recorder
 /// single record of mouse event
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MouseEventName {
     // 1
     LeftDown,
@@ -245,126 +381,87 @@ pub struct MouseActionSerializable {
 }
 
 impl MouseAction {
-    pub fn from_string(string_source: &str) -> Result<MouseAction, ()> {
-        let action_string_code: SerdeResult<MouseActionSerializable> = json_parse(string_source);
-        match action_string_code {
-            Ok(_action) => {
-                let mut action = MouseAction {
-                    evs: vec![],
-                    till: _action.till,
-                };
-
-                for ev in _action.evs.iter() {
-                    match ev.ev_name {
-                        1 => {
-                            action.evs.push(MouseEv {
-                                ev_name: MouseEventName::LeftDown,
-                                position: (ev.position[0], ev.position[1]),
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        2 => {
-                            action.evs.push(MouseEv {
-                                ev_name: MouseEventName::LeftUp,
-                                position: (ev.position[0], ev.position[1]),
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        3 => {
-                            action.evs.push(MouseEv {
-                                ev_name: MouseEventName::RightDown,
-                                position: (ev.position[0], ev.position[1]),
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        4 => {
-                            action.evs.push(MouseEv {
-                                ev_name: MouseEventName::RightUp,
-                                position: (ev.position[0], ev.position[1]),
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        5 => {
-                            action.evs.push(MouseEv {
-                                ev_name: MouseEventName::MidDown,
-                                position: (ev.position[0], ev.position[1]),
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        6 => {
-                            action.evs.push(MouseEv {
-                                ev_name: MouseEventName::MidUp,
-                                position: (ev.position[0], ev.position[1]),
-                                timestamp: ev.timestamp,
-                            })
-                        }
-                        _ => ()
-                    }
-                }
+    pub fn from_string(string_source: &str) -> Result<MouseAction, Error> {
+        let (action, _) = MouseAction::from_string_lenient(string_source)?;
+        Ok(action)
+    }
 
-                Ok(action)
-            }
-            Err(_) => Err(())
+    /// `from_string` but fails with the index and the `ev_name` of the first event whose `ev_name` is out of 1-6,
+    /// instead of leaving it out silently.
+    pub fn from_string_strict(string_source: &str) -> Result<MouseAction, Error> {
+        let (action, mut skipped) = MouseAction::from_string_lenient(string_source)?;
+        match skipped.is_empty() {
+            true => Ok(action),
+            false => Err(skipped.remove(0).into_error())
         }
     }
 
-    pub fn to_string(&self) -> Result<String, ()> {
-        let mut action_string_code = MouseActionSerializable {
+    /// `from_string` but the events left out (with `ev_name` out of 1-6) are returned beside the action.
+    pub fn from_string_lenient(string_source: &str) -> Result<(MouseAction, Vec<SkippedEvent>), Error> {
+        let document: Document<MouseActionSerializable> = parse_document(string_source, "mouse")?;
+        Ok(MouseAction::from_serializable(&document.action))
+    }
+
+    /// `from_string` but the metadata in the header is returned beside the action.
+    /// (`Metadata::default()` for the records of v0.1 which have no header)
+    pub fn from_string_with_meta(string_source: &str) -> Result<(MouseAction, Metadata), Error> {
+        let document: Document<MouseActionSerializable> = parse_document(string_source, "mouse")?;
+        Ok((MouseAction::from_serializable(&document.action).0, document.meta))
+    }
+
+    /// Events whose `ev_name` is out of 1-6 are left out and returned beside the action.
+    pub fn from_serializable(source: &MouseActionSerializable) -> (MouseAction, Vec<SkippedEvent>) {
+        let mut action = MouseAction {
             evs: vec![],
-            till: self.till,
+            till: source.till,
         };
-
-        for ev in self.evs.iter() {
-            match ev.ev_name {
-                MouseEventName::LeftDown => {
-                    action_string_code.evs.push(MouseEvSerializable {
-                        ev_name: 1,
-                        position: [ev.position.0, ev.position.1],
-                        timestamp: ev.timestamp,
-                    })
-                }
-                MouseEventName::LeftUp => {
-                    action_string_code.evs.push(MouseEvSerializable {
-                        ev_name: 2,
-                        position: [ev.position.0, ev.position.1],
-                        timestamp: ev.timestamp,
-                    })
-                }
-                MouseEventName::RightDown => {
-                    action_string_code.evs.push(MouseEvSerializable {
-                        ev_name: 3,
-                        position: [ev.position.0, ev.position.1],
-                        timestamp: ev.timestamp,
-                    })
-                }
-                MouseEventName::RightUp => {
-                    action_string_code.evs.push(MouseEvSerializable {
-                        ev_name: 4,
-                        position: [ev.position.0, ev.position.1],
-                        timestamp: ev.timestamp,
-                    })
-                }
-                MouseEventName::MidDown => {
-                    action_string_code.evs.push(MouseEvSerializable {
-                        ev_name: 5,
-                        position: [ev.position.0, ev.position.1],
-                        timestamp: ev.timestamp,
-                    })
-                }
-                MouseEventName::MidUp => {
-                    action_string_code.evs.push(MouseEvSerializable {
-                        ev_name: 6,
-                        position: [ev.position.0, ev.position.1],
+        let mut skipped = vec![];
+
+        for (index, ev) in source.evs.iter().enumerate() {
+            match MouseMapper::number_to_ev_name(ev.ev_name) {
+                Ok(ev_name) => {
+                    action.evs.push(MouseEv {
+                        ev_name,
+                        position: (ev.position[0], ev.position[1]),
                         timestamp: ev.timestamp,
                     })
                 }
+                Err(reason) => skipped.push(SkippedEvent { index, reason })
             }
         }
 
-        match json_stringify(&action_string_code) {
+        (action, skipped)
+    }
+
+    /// serialize to the latest document (see `FORMAT_VERSION`) with the metadata of this machine (`Metadata::of_host`).
+    pub fn to_string(&self) -> Result<String, Error> {
+        self.to_string_with_meta(&Metadata::of_host())
+    }
+
+    /// `to_string` with the given metadata in the header.
+    pub fn to_string_with_meta(&self, meta: &Metadata) -> Result<String, Error> {
+        let document = Document::new("mouse", meta.clone(), self.to_serializable());
+        match json_stringify(&document) {
             Ok(s) => Ok(s),
-            Err(_) => Err(())
+            Err(err) => Err(Error::from(err))
+        }
+    }
+
+    pub fn to_serializable(&self) -> MouseActionSerializable {
+        let mut action_string_code = MouseActionSerializable {
+            evs: vec![],
+            till: self.till,
+        };
+
+        for ev in self.evs.iter() {
+            action_string_code.evs.push(MouseEvSerializable {
+                ev_name: MouseMapper::ev_name_to_number(ev.ev_name),
+                position: [ev.position.0, ev.position.1],
+                timestamp: ev.timestamp,
+            })
         }
+
+        action_string_code
     }
 }
 
@@ -374,6 +471,8 @@ pub struct MouseRecorder {
     /// Here, we've wrapped your vector in a Arc<Mutex<>> so we can
     /// write to it inside our closure.
     ev_queue: Arc<Mutex<Vec<MouseEv>>>,
+    /// metadata of the last record
+    meta: Metadata,
 }
 
 impl MouseRecorder {
@@ -381,6 +480,7 @@ impl MouseRecorder {
         MouseRecorder {
             recording: Arc::new(Mutex::new(false)),
             ev_queue: Arc::new(Mutex::new(vec![])),
+            meta: Metadata::default(),
         }
     }
 
@@ -388,19 +488,44 @@ impl MouseRecorder {
         (*self.ev_queue.lock().unwrap()).clone()
     }
 
+    /// metadata of the last record (time, machine, stop key and cursor position at start),
+    /// to be written with `MouseAction.to_string_with_meta`.
+    pub fn get_metadata(&self) -> Metadata {
+        self.meta.clone()
+    }
+
     /// Doing record work in main thread.
     /// Anyway, the listener(s) is working in separator thread(s).
     /// But the guard(s) of listener(s) can only live in the scope of this function.
     /// So this call need to be 'block' until you press the key representing 'stop_code'.
     pub fn do_record(&mut self, stop_code: Keycode) -> MouseAction {
-        // start recording: clear records and set the signal
-        *self.ev_queue.lock().unwrap() = vec![];
-        *self.recording.lock().unwrap() = true;
+        self.record(stop_code, None).expect("a record without journal does not fail")
+    }
 
+    /// `do_record` but every event is also appended to the journal at `path` as it happens
+    /// (see `JournalWriter`), so that a crash does not lose the record.
+    /// `MouseAction::read_journal` rebuilds the action from the file.
+    /// It fails before recording if the journal can not be written, or when it stops with the error of the first
+    /// failed write (the journal is dropped then, but the record goes on, see `get_record`).
+    pub fn do_record_with_journal<P: AsRef<Path>>(&mut self, stop_code: Keycode, path: P) -> Result<MouseAction, Error> {
+        let file = File::create(path)?;
+        self.record(stop_code, Some(file))
+    }
+
+    fn record(&mut self, stop_code: Keycode, journal: Option<File>) -> Result<MouseAction, Error> {
         // instance
         let device_state = DeviceState::new();
-        // record start time as zero
+        self.meta = Metadata::of_record(&device_state, stop_code);
+        // record start time as zero, right after `created_at` is stamped so that records of the same session can be
+        // aligned by it (see `CombinedAction::merge_by_start`)
         let timeline = Instant::now();
+        let journal: SharedJournal = share_journal(
+            journal.map(|file| JournalWriter::new(file, "mouse", &self.meta)).transpose()?
+        );
+
+        // start recording: clear records and set the signal
+        *self.ev_queue.lock().unwrap() = vec![];
+        *self.recording.lock().unwrap() = true;
 
         // region an extra listener to watch the stop signal
         let recording = Arc::clone(&self.recording);
@@ -415,69 +540,47 @@ impl MouseRecorder {
 
         // region mouse down
         let ev_queue_down = Arc::clone(&self.ev_queue);
+        let journal_down = Arc::clone(&journal);
         let device_state_down = DeviceState::new();
         let _guard_down = device_state.on_mouse_down(move |btn| {
-            match *btn {
-                1 => {
-                    let mut ev_queue_down = ev_queue_down.lock().unwrap();
-                    ev_queue_down.push(MouseEv {
-                        ev_name: MouseEventName::LeftDown,
-                        position: device_state_down.get_mouse().coords,
-                        timestamp: timeline.elapsed().as_millis() as u64,
-                    });
-                }
-                2 => {
-                    let mut ev_queue_down = ev_queue_down.lock().unwrap();
-                    ev_queue_down.push(MouseEv {
-                        ev_name: MouseEventName::RightDown,
-                        position: device_state_down.get_mouse().coords,
-                        timestamp: timeline.elapsed().as_millis() as u64,
-                    });
-                }
-                3 => {
-                    let mut ev_queue_down = ev_queue_down.lock().unwrap();
-                    ev_queue_down.push(MouseEv {
-                        ev_name: MouseEventName::MidDown,
-                        position: device_state_down.get_mouse().coords,
-                        timestamp: timeline.elapsed().as_millis() as u64,
-                    });
-                }
-                _ => ()  // ignore other button event
-            }
+            let ev_name = match *btn {
+                1 => MouseEventName::LeftDown,
+                2 => MouseEventName::RightDown,
+                3 => MouseEventName::MidDown,
+                _ => return  // ignore other button event
+            };
+            let ev = MouseEv {
+                ev_name,
+                position: device_state_down.get_mouse().coords,
+                timestamp: timeline.elapsed().as_millis() as u64,
+            };
+            append_journal(&journal_down, |journal| journal.write_mouse_ev(&ev));
+
+            let mut ev_queue_down = ev_queue_down.lock().unwrap();
+            ev_queue_down.push(ev);
         });
         // endregion
 
         // region mouse up
         let ev_queue_up = Arc::clone(&self.ev_queue);
+        let journal_up = Arc::clone(&journal);
         let device_state_up = DeviceState::new();
         let _guard_up = device_state.on_mouse_up(move |btn| {
-            match *btn {
-                1 => {
-                    let mut ev_queue_up = ev_queue_up.lock().unwrap();
-                    ev_queue_up.push(MouseEv {
-                        ev_name: MouseEventName::LeftUp,
-                        position: device_state_up.get_mouse().coords,
-                        timestamp: timeline.elapsed().as_millis() as u64,
-                    });
-                }
-                2 => {
-                    let mut ev_queue_up = ev_queue_up.lock().unwrap();
-                    ev_queue_up.push(MouseEv {
-                        ev_name: MouseEventName::RightUp,
-                        position: device_state_up.get_mouse().coords,
-                        timestamp: timeline.elapsed().as_millis() as u64,
-                    });
-                }
-                3 => {
-                    let mut ev_queue_up = ev_queue_up.lock().unwrap();
-                    ev_queue_up.push(MouseEv {
-                        ev_name: MouseEventName::MidUp,
-                        position: device_state_up.get_mouse().coords,
-                        timestamp: timeline.elapsed().as_millis() as u64,
-                    });
-                }
-                _ => ()  // ignore other button event
-            }
+            let ev_name = match *btn {
+                1 => MouseEventName::LeftUp,
+                2 => MouseEventName::RightUp,
+                3 => MouseEventName::MidUp,
+                _ => return  // ignore other button event
+            };
+            let ev = MouseEv {
+                ev_name,
+                position: device_state_up.get_mouse().coords,
+                timestamp: timeline.elapsed().as_millis() as u64,
+            };
+            append_journal(&journal_up, |journal| journal.write_mouse_ev(&ev));
+
+            let mut ev_queue_up = ev_queue_up.lock().unwrap();
+            ev_queue_up.push(ev);
         });
         // endregion
 
@@ -485,10 +588,12 @@ impl MouseRecorder {
         loop {
             if !*self.recording.lock().unwrap() {
                 // jump out of the loop, the guard(s) will `drop` then.
-                return MouseAction {
+                let till = timeline.elapsed().as_millis() as u64;
+                finish_journal(&journal, till)?;
+                return Ok(MouseAction {
                     evs: (*self.ev_queue.lock().unwrap()).clone(),
-                    till: timeline.elapsed().as_millis() as u64,
-                };
+                    till,
+                });
             }
         }
     }
@@ -500,6 +605,66 @@ impl MouseRecorder {
 mod test {
     use super::*;
 
+    /// 未知按键: 严格模式报错, 宽松模式返回被跳过的事件
+    #[test]
+    fn keyboard_action_strict() {
+        let source = "{\"evs\":[{\"code\":\"KeyA\",\"press\":true,\"timestamp\":10},{\"code\":\"KeyFoo\",\"press\":true,\"timestamp\":20},{\"code\":\"KeyA\",\"press\":false,\"timestamp\":30}],\"till\":40}";
+
+        match KeyboardAction::from_string_strict(source) {
+            Err(Error::Event { index, cause }) => {
+                assert_eq!(index, 1);
+                assert!(matches!(*cause, Error::UnknownKey(ref code) if code == "KeyFoo"));
+            }
+            _ => panic!("expect an invalid event"),
+        }
+
+        let (action, skipped) = KeyboardAction::from_string_lenient(source).unwrap();
+        assert_eq!(action.evs.len(), 2);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, 1);
+
+        assert_eq!(KeyboardAction::from_string(source).unwrap().evs.len(), 2);
+    }
+
+    /// 录制得到的按键都有前端 code, 各种格式都不会丢失
+    #[test]
+    fn keyboard_action_all_keys() {
+        let mut action = KeyboardAction { evs: vec![], till: 100 };
+        for code in [Keycode::Insert, Keycode::BackSlash, Keycode::Numpad5, Keycode::RShift] {
+            action.evs.push(KeyboardEv { code, press: true, timestamp: 10 });
+            action.evs.push(KeyboardEv { code, press: false, timestamp: 20 });
+        }
+
+        let again = KeyboardAction::from_string_strict(&action.to_string().unwrap()).unwrap();
+        assert_eq!(again.evs.iter().map(|ev| ev.code).collect::<Vec<_>>(), action.evs.iter().map(|ev| ev.code).collect::<Vec<_>>());
+        assert!(action.to_serializable_lenient(&KeyMap::new()).1.is_empty());
+
+        let mut csv = vec![];
+        action.write_csv(&mut csv).unwrap();
+        assert_eq!(KeyboardAction::read_csv(csv.as_slice()).unwrap().evs.len(), 8);
+        let mut journal = vec![];
+        action.write_journal(&mut journal, &Metadata::default()).unwrap();
+        assert_eq!(KeyboardAction::read_journal(journal.as_slice()).unwrap().0.evs.len(), 8);
+    }
+
+    /// 未知鼠标事件: 严格模式报错, 宽松模式返回被跳过的事件
+    #[test]
+    fn mouse_action_strict() {
+        let source = "{\"evs\":[{\"ev_name\":7,\"position\":[1,2],\"timestamp\":10},{\"ev_name\":1,\"position\":[1,2],\"timestamp\":20}],\"till\":40}";
+
+        match MouseAction::from_string_strict(source) {
+            Err(Error::Event { index, cause }) => {
+                assert_eq!(index, 0);
+                assert!(matches!(*cause, Error::UnknownMouseEvent(7)));
+            }
+            _ => panic!("expect an invalid event"),
+        }
+
+        let (action, skipped) = MouseAction::from_string_lenient(source).unwrap();
+        assert_eq!(action.evs.len(), 1);
+        assert_eq!(skipped[0].index, 0);
+    }
+
     /// 键盘行为录制测试 - 无断言, 需要自行判断输出是否正确
     #[test]
     fn keyboard_recorder() {
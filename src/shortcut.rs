@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use device_query::Keycode;
+use crate::{Error, KeyboardAction, KeyboardEv, KeyboardMapper};
+
+// region shortcut
+/// the modifiers of a `Shortcut`, the left and the right keys are the same
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    /// the modifiers among `keys`, e.g. the held keys of `DeviceState.get_keys`
+    pub fn of<'a, I: IntoIterator<Item=&'a Keycode>>(keys: I) -> Modifiers {
+        let mut modifiers = Modifiers::default();
+        for key in keys {
+            match key {
+                Keycode::LControl | Keycode::RControl => modifiers.ctrl = true,
+                Keycode::LAlt | Keycode::RAlt => modifiers.alt = true,
+                Keycode::LShift | Keycode::RShift => modifiers.shift = true,
+                Keycode::Meta => modifiers.meta = true,
+                _ => ()
+            }
+        }
+        modifiers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.ctrl || self.alt || self.shift || self.meta)
+    }
+
+    /// the keys pressed to play the modifiers, in the order of the names
+    fn keys(&self) -> Vec<Keycode> {
+        [(self.ctrl, Keycode::LControl), (self.alt, Keycode::LAlt), (self.shift, Keycode::LShift), (self.meta, Keycode::Meta)]
+            .iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, key)| *key)
+            .collect()
+    }
+}
+
+/// if the key is one of the modifiers
+pub fn is_modifier(key: Keycode) -> bool {
+    !Modifiers::of(&[key]).is_empty()
+}
+
+/// A key pressed while modifiers are held, e.g. `Ctrl+Shift+K`.
+/// It displays as the modifiers in the order `Ctrl`, `Alt`, `Shift`, `Meta` then the key,
+/// and parses from the same (see `FromStr`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    pub modifiers: Modifiers,
+    /// not a modifier
+    pub key: Keycode,
+}
+
+impl Shortcut {
+    pub fn new(modifiers: Modifiers, key: Keycode) -> Shortcut {
+        Shortcut { modifiers, key }
+    }
+
+    /// if the shortcut needs `Ctrl`, `Alt` or `Meta`, so that it types nothing
+    pub fn is_chord(&self) -> bool {
+        self.modifiers.ctrl || self.modifiers.alt || self.modifiers.meta
+    }
+
+    /// If exactly the keys of the shortcut are among `keys`, e.g. the held keys of `DeviceState.get_keys`.
+    /// Other modifiers held make it false, other keys do not.
+    pub fn is_pressed(&self, keys: &[Keycode]) -> bool {
+        keys.contains(&self.key) && Modifiers::of(keys) == self.modifiers
+    }
+
+    /// The events to play the shortcut from `start`, `step` ms apart:
+    /// the modifiers are pressed, the key is pressed and released, then the modifiers are released the other way round.
+    pub fn to_events(&self, start: u64, step: u64) -> Vec<KeyboardEv> {
+        let modifiers = self.modifiers.keys();
+        let mut evs = vec![];
+        let mut push = |code: Keycode, press: bool| {
+            let timestamp = start + evs.len() as u64 * step;
+            evs.push(KeyboardEv { code, press, timestamp });
+        };
+        for key in modifiers.iter() {
+            push(*key, true);
+        }
+        push(self.key, true);
+        push(self.key, false);
+        for key in modifiers.iter().rev() {
+            push(*key, false);
+        }
+        evs
+    }
+
+    /// `to_events` as an action, which ends at the last event
+    pub fn to_action(&self, step: u64) -> KeyboardAction {
+        let evs = self.to_events(0, step);
+        let till = evs.last().map(|ev| ev.timestamp).unwrap_or(0);
+        KeyboardAction { evs, till }
+    }
+}
+
+/// The name of a key in a shortcut: `K` and `5` for the letters and the digits, else `code` in frontend,
+/// or the name in device_query for the keys without one.
+fn key_name(key: Keycode) -> String {
+    match KeyboardMapper::dq_to_front(key) {
+        Some(front) => String::from(front.strip_prefix("Key").or_else(|| front.strip_prefix("Digit")).unwrap_or(front)),
+        None => format!("{:?}", key)
+    }
+}
+
+/// see `key_name`, with a few short names (`Esc`, `Up`, `PgDn`, ...) and case-insensitive for single characters
+fn parse_key_name(name: &str) -> Option<Keycode> {
+    let front = match name {
+        "Esc" => String::from("Escape"),
+        "Return" => String::from("Enter"),
+        "Del" => String::from("Delete"),
+        "Up" | "Down" | "Left" | "Right" => format!("Arrow{}", name),
+        "PgUp" => String::from("PageUp"),
+        "PgDn" => String::from("PageDown"),
+        _ if name.len() == 1 && name.chars().all(|c| c.is_ascii_alphabetic()) => format!("Key{}", name.to_ascii_uppercase()),
+        _ if name.len() == 1 && name.chars().all(|c| c.is_ascii_digit()) => format!("Digit{}", name),
+        _ => String::from(name)
+    };
+    KeyboardMapper::front_to_dq(&front)
+        .or_else(|| name.parse::<Keycode>().ok())
+        .filter(|key| !is_modifier(*key))
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.modifiers.ctrl, "Ctrl"), (self.modifiers.alt, "Alt"), (self.modifiers.shift, "Shift"), (self.modifiers.meta, "Meta")] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+/// Parse `Ctrl+Shift+K`: modifiers (`Ctrl`/`Control`, `Alt`/`Option`, `Shift`, `Meta`/`Super`/`Win`/`Cmd`,
+/// in any order and case) joined by `+` with a key at the end, see `key_name`.
+impl FromStr for Shortcut {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Shortcut, Error> {
+        let error = |column: usize, message: String| Error::Parse { line: 1, column, message };
+        let mut modifiers = Modifiers::default();
+        let mut column = 1;
+        let parts: Vec<&str> = source.split('+').collect();
+
+        for (index, part) in parts.iter().enumerate() {
+            let name = part.trim();
+            if name.is_empty() {
+                return Err(error(column, String::from("expect a key")));
+            }
+            if index + 1 == parts.len() {
+                return match parse_key_name(name) {
+                    Some(key) => Ok(Shortcut { modifiers, key }),
+                    None if parse_modifier(name).is_some() => Err(error(column, String::from("expect a key after the modifiers"))),
+                    None => Err(Error::UnknownKey(String::from(name)))
+                };
+            }
+            let held = match parse_modifier(name) {
+                Some(Keycode::LControl) => &mut modifiers.ctrl,
+                Some(Keycode::LAlt) => &mut modifiers.alt,
+                Some(Keycode::LShift) => &mut modifiers.shift,
+                Some(_) => &mut modifiers.meta,
+                None => return Err(error(column, format!("unknown modifier `{}`", name)))
+            };
+            if *held {
+                return Err(error(column, format!("repeated modifier `{}`", name)));
+            }
+            *held = true;
+            column += part.chars().count() + 1;
+        }
+        unreachable!("split returns at least one part")
+    }
+}
+
+fn parse_modifier(name: &str) -> Option<Keycode> {
+    match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Some(Keycode::LControl),
+        "alt" | "option" => Some(Keycode::LAlt),
+        "shift" => Some(Keycode::LShift),
+        "meta" | "super" | "win" | "cmd" | "command" => Some(Keycode::Meta),
+        _ => None
+    }
+}
+// endregion
+
+// region detection
+/// Find shortcuts in a stream of events as they come, e.g. from a recorder.
+#[derive(Default)]
+pub struct ShortcutDetector {
+    held: HashSet<Keycode>,
+}
+
+impl ShortcutDetector {
+    pub fn new() -> ShortcutDetector {
+        ShortcutDetector { held: HashSet::new() }
+    }
+
+    /// The shortcut pressed by `ev`: a press of a key which is not a modifier, with the modifiers held then
+    /// (maybe none). The repeated presses of a held key are not shortcuts.
+    pub fn feed(&mut self, ev: &KeyboardEv) -> Option<Shortcut> {
+        match ev.press {
+            true => {
+                if !self.held.insert(ev.code) || is_modifier(ev.code) {
+                    return None;
+                }
+                Some(Shortcut::new(Modifiers::of(self.held.iter()), ev.code))
+            }
+            false => {
+                self.held.remove(&ev.code);
+                None
+            }
+        }
+    }
+}
+
+impl KeyboardAction {
+    /// the chords (see `Shortcut.is_chord`) pressed in the action with their timestamps
+    pub fn shortcuts(&self) -> Vec<(u64, Shortcut)> {
+        let mut detector = ShortcutDetector::new();
+        self.evs.iter()
+            .filter_map(|ev| detector.feed(ev).map(|shortcut| (ev.timestamp, shortcut)))
+            .filter(|(_, shortcut)| shortcut.is_chord())
+            .collect()
+    }
+
+    /// the timestamps where `shortcut` is pressed, with exactly its modifiers
+    pub fn find_shortcut(&self, shortcut: &Shortcut) -> Vec<u64> {
+        let mut detector = ShortcutDetector::new();
+        self.evs.iter()
+            .filter(|ev| detector.feed(ev).as_ref() == Some(shortcut))
+            .map(|ev| ev.timestamp)
+            .collect()
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let shortcut: Shortcut = "ctrl+shift+k".parse().unwrap();
+        assert!(shortcut.modifiers.ctrl && shortcut.modifiers.shift && !shortcut.modifiers.alt);
+        assert_eq!(shortcut.key, Keycode::K);
+        assert_eq!(shortcut.to_string(), "Ctrl+Shift+K");
+        assert_eq!("Shift+Win+Alt+F5".parse::<Shortcut>().unwrap().to_string(), "Alt+Shift+Meta+F5");
+        assert_eq!("Esc".parse::<Shortcut>().unwrap().key, Keycode::Escape);
+        assert_eq!("Ctrl + 1".parse::<Shortcut>().unwrap().to_string(), "Ctrl+1");
+
+        assert!(matches!("Ctrl+Hyper+K".parse::<Shortcut>(), Err(Error::Parse { column: 6, .. })));
+        assert!(matches!("Ctrl+Ctrl+K".parse::<Shortcut>(), Err(Error::Parse { column: 6, .. })));
+        assert!(matches!("Ctrl+Shift".parse::<Shortcut>(), Err(Error::Parse { column: 6, .. })));
+        assert!(matches!("Ctrl+".parse::<Shortcut>(), Err(Error::Parse { column: 6, .. })));
+        assert!(matches!("Ctrl+Nope".parse::<Shortcut>(), Err(Error::UnknownKey(key)) if key == "Nope"));
+
+        // keys with longer names
+        for key in [Keycode::Insert, Keycode::Numpad5, Keycode::BackSlash] {
+            let shortcut = Shortcut::new(Modifiers { ctrl: true, ..Modifiers::default() }, key);
+            assert_eq!(shortcut.to_string().parse::<Shortcut>().unwrap(), shortcut);
+        }
+    }
+
+    #[test]
+    fn detect_and_expand() {
+        let shortcut: Shortcut = "Ctrl+Shift+K".parse().unwrap();
+        let mut action = shortcut.to_action(10);
+        let keys: Vec<(Keycode, bool)> = action.evs.iter().map(|ev| (ev.code, ev.press)).collect();
+        assert_eq!(keys, vec![
+            (Keycode::LControl, true),
+            (Keycode::LShift, true),
+            (Keycode::K, true),
+            (Keycode::K, false),
+            (Keycode::LShift, false),
+            (Keycode::LControl, false),
+        ]);
+        assert_eq!(action.till, 50);
+
+        // typing a capital is not a chord
+        action.evs.extend(Shortcut::new(Modifiers { shift: true, ..Modifiers::default() }, Keycode::A).to_events(100, 10));
+        assert_eq!(action.shortcuts(), vec![(20, shortcut)]);
+        assert_eq!(action.find_shortcut(&"Shift+A".parse().unwrap()), vec![110]);
+        assert!(action.find_shortcut(&"Ctrl+K".parse().unwrap()).is_empty());
+
+        assert!(shortcut.is_pressed(&[Keycode::K, Keycode::RShift, Keycode::LControl]));
+        assert!(!shortcut.is_pressed(&[Keycode::K, Keycode::LShift, Keycode::LControl, Keycode::LAlt]));
+    }
+}
+// endregion
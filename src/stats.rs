@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, HashMap};
+use device_query::Keycode;
+use serde::{Deserialize, Serialize};
+use crate::{KeyboardAction, KeyboardMapper, MouseAction, MouseEventName, MouseMapper, ShortcutDetector};
+
+// region report
+/// longest time (ms) between the presses of a double click
+pub const DOUBLE_CLICK_INTERVAL: u64 = 500;
+/// farthest distance (px, on each axis) between the presses of a double click
+pub const DOUBLE_CLICK_DISTANCE: i32 = 4;
+
+/// summary of durations in ms, all 0 without samples
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub median: u64,
+    /// 90th percentile (nearest rank)
+    pub p90: u64,
+}
+
+impl Distribution {
+    pub fn of(mut samples: Vec<u64>) -> Distribution {
+        if samples.is_empty() {
+            return Distribution::default();
+        }
+        samples.sort_unstable();
+        let count = samples.len();
+        // nearest rank, from 1
+        let rank = |percent: usize| samples[((percent * count + 99) / 100).max(1) - 1];
+        Distribution {
+            count,
+            min: samples[0],
+            max: samples[count - 1],
+            mean: samples.iter().sum::<u64>() as f64 / count as f64,
+            median: rank(50),
+            p90: rank(90),
+        }
+    }
+}
+
+/// a shortcut and how many times it is pressed
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ShortcutCount {
+    /// e.g. `Ctrl+Shift+K`
+    pub shortcut: String,
+    pub count: usize,
+}
+
+/// statistics of a `KeyboardAction`, see `KeyboardAction.stats`
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct KeyboardStats {
+    /// key presses, the repeated presses of a held key are not counted
+    pub total_keys: usize,
+    /// presses of each key, by `code` in frontend
+    pub key_counts: BTreeMap<String, usize>,
+    /// words (5 characters) per minute of the typed text (see `to_text`), from the first character to the last
+    pub wpm: f64,
+    /// press to release of the same key
+    pub dwell: Distribution,
+    /// release of a key to the next press
+    pub flight: Distribution,
+    /// the chords (see `Shortcut.is_chord`) by their names, the most used first
+    pub shortcuts: Vec<ShortcutCount>,
+}
+
+/// statistics of a `MouseAction`, see `MouseAction.stats`
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct MouseStats {
+    /// presses of each button: `left`, `right` or `middle`
+    pub clicks: BTreeMap<String, usize>,
+    /// second presses of a button within `DOUBLE_CLICK_INTERVAL` and `DOUBLE_CLICK_DISTANCE` of the first
+    pub double_clicks: usize,
+    /// `double_clicks` over all the clicks, 0 without clicks
+    pub double_click_rate: f64,
+    /// px between the positions of the events one after another, the recorder keeps no moves between them
+    pub distance: f64,
+}
+
+fn button_name(ev_name: MouseEventName) -> &'static str {
+    match MouseMapper::ev_name_to_dq(ev_name).0 {
+        2 => "right",
+        3 => "middle",
+        _ => "left"
+    }
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    /// Statistics of the keystrokes, for ergonomics and for tuning the timing of macros.
+    /// The events should be in order, see `normalize`.
+    pub fn stats(&self) -> KeyboardStats {
+        let mut stats = KeyboardStats::default();
+        // the held keys with the time they are pressed
+        let mut held: HashMap<Keycode, u64> = HashMap::new();
+        let mut last_release: Option<u64> = None;
+        let mut dwell = vec![];
+        let mut flight = vec![];
+        let mut shortcuts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut detector = ShortcutDetector::new();
+
+        for ev in self.evs.iter() {
+            if let Some(shortcut) = detector.feed(ev).filter(|shortcut| shortcut.is_chord()) {
+                *shortcuts.entry(shortcut.to_string()).or_insert(0) += 1;
+            }
+            match ev.press {
+                true => {
+                    if held.contains_key(&ev.code) {
+                        continue;
+                    }
+                    stats.total_keys += 1;
+                    if let Some(code) = KeyboardMapper::dq_to_front(ev.code) {
+                        *stats.key_counts.entry(String::from(code)).or_insert(0) += 1;
+                    }
+                    if let Some(release) = last_release.take() {
+                        flight.push(ev.timestamp.saturating_sub(release));
+                    }
+                    held.insert(ev.code, ev.timestamp);
+                }
+                false => {
+                    if let Some(press) = held.remove(&ev.code) {
+                        dwell.push(ev.timestamp.saturating_sub(press));
+                    }
+                    last_release = Some(ev.timestamp);
+                }
+            }
+        }
+
+        stats.dwell = Distribution::of(dwell);
+        stats.flight = Distribution::of(flight);
+
+        let typed = self.to_text();
+        if let (Some(first), Some(last)) = (typed.timestamps.first(), typed.timestamps.last()) {
+            if last > first {
+                let minutes = (last - first) as f64 / 60000.0;
+                stats.wpm = typed.text.chars().count() as f64 / 5.0 / minutes;
+            }
+        }
+
+        stats.shortcuts = shortcuts.into_iter().map(|(shortcut, count)| ShortcutCount { shortcut, count }).collect();
+        // stable, the same counts stay by name
+        stats.shortcuts.sort_by_key(|shortcut| std::cmp::Reverse(shortcut.count));
+        stats
+    }
+}
+
+impl MouseAction {
+    /// Statistics of the clicks and of the way the mouse travels.
+    pub fn stats(&self) -> MouseStats {
+        let mut stats = MouseStats::default();
+        // the last press of each button, and if it was the second of a double click
+        let mut last_press: HashMap<usize, (u64, (i32, i32), bool)> = HashMap::new();
+        let mut total = 0;
+
+        for (index, ev) in self.evs.iter().enumerate() {
+            if index > 0 {
+                let (x, y) = self.evs[index - 1].position;
+                let (dx, dy) = ((ev.position.0 - x) as f64, (ev.position.1 - y) as f64);
+                stats.distance += (dx * dx + dy * dy).sqrt();
+            }
+
+            let (button, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+            if !press {
+                continue;
+            }
+            total += 1;
+            *stats.clicks.entry(String::from(button_name(ev.ev_name))).or_insert(0) += 1;
+
+            // a triple click is one double click
+            let double = match last_press.get(&button) {
+                Some((timestamp, (x, y), false)) => ev.timestamp.saturating_sub(*timestamp) <= DOUBLE_CLICK_INTERVAL &&
+                    (ev.position.0 - x).abs() <= DOUBLE_CLICK_DISTANCE &&
+                    (ev.position.1 - y).abs() <= DOUBLE_CLICK_DISTANCE,
+                _ => false
+            };
+            if double {
+                stats.double_clicks += 1;
+            }
+            last_press.insert(button, (ev.timestamp, ev.position, double));
+        }
+
+        if total > 0 {
+            stats.double_click_rate = stats.double_clicks as f64 / total as f64;
+        }
+        stats
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{KeyboardEv, MouseEv, TypingProfile};
+
+    #[test]
+    fn keyboard_stats() {
+        // 10 characters in 900 ms with a dwell of 50 and a flight of 50
+        let mut action = KeyboardAction::from_text("hello toca", TypingProfile::fixed(50, 50)).unwrap();
+        let mut shortcut = vec![
+            KeyboardEv { code: Keycode::LControl, press: true, timestamp: 2000 },
+            KeyboardEv { code: Keycode::LShift, press: true, timestamp: 2010 },
+            KeyboardEv { code: Keycode::K, press: true, timestamp: 2020 },
+            KeyboardEv { code: Keycode::K, press: true, timestamp: 2050 },
+            KeyboardEv { code: Keycode::K, press: false, timestamp: 2100 },
+            KeyboardEv { code: Keycode::LShift, press: false, timestamp: 2110 },
+            KeyboardEv { code: Keycode::LControl, press: false, timestamp: 2120 },
+        ];
+        action.evs.append(&mut shortcut);
+        action.till = 2200;
+
+        let stats = action.stats();
+        assert_eq!(stats.total_keys, 13);
+        assert_eq!(stats.key_counts["KeyL"], 2);
+        assert_eq!(stats.key_counts["KeyK"], 1);
+        assert_eq!(stats.shortcuts, vec![ShortcutCount { shortcut: String::from("Ctrl+Shift+K"), count: 1 }]);
+        assert!((stats.wpm - 2.0 / (900.0 / 60000.0)).abs() < 1e-6);
+        assert_eq!(stats.dwell.median, 50);
+        assert_eq!(stats.dwell.max, 120);
+        assert_eq!(stats.flight.min, 50);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(serde_json::from_str::<KeyboardStats>(&json).unwrap(), stats);
+    }
+
+    #[test]
+    fn mouse_stats() {
+        let click = |ev_name: MouseEventName, x: i32, timestamp: u64| MouseEv { ev_name, position: (x, 0), timestamp };
+        let action = MouseAction {
+            evs: vec![
+                // a double click
+                click(MouseEventName::LeftDown, 0, 0),
+                click(MouseEventName::LeftUp, 0, 50),
+                click(MouseEventName::LeftDown, 2, 200),
+                click(MouseEventName::LeftUp, 2, 250),
+                // too far for a double click
+                click(MouseEventName::RightDown, 100, 1000),
+                click(MouseEventName::RightUp, 100, 1050),
+                click(MouseEventName::RightDown, 200, 1100),
+                click(MouseEventName::RightUp, 200, 1150),
+            ],
+            till: 1200,
+        };
+
+        let stats = action.stats();
+        assert_eq!(stats.clicks["left"], 2);
+        assert_eq!(stats.clicks["right"], 2);
+        assert_eq!(stats.double_clicks, 1);
+        assert_eq!(stats.double_click_rate, 0.25);
+        assert_eq!(stats.distance, 200.0);
+    }
+
+    #[test]
+    fn distribution() {
+        let distribution = Distribution::of((1..=10).collect());
+        assert_eq!(distribution.count, 10);
+        assert_eq!(distribution.mean, 5.5);
+        assert_eq!(distribution.median, 5);
+        assert_eq!(distribution.p90, 9);
+        assert_eq!(Distribution::of(vec![]), Distribution::default());
+    }
+}
+// endregion
@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+use device_query::Keycode;
+use crate::{Error, KeyboardAction, KeyboardEv, KeyboardLayout, Rng};
+
+// region text <=> action
+/// the result of `KeyboardAction.to_text`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypedText {
+    /// the final text
+    pub text: String,
+    /// timestamp of the key press that typed each character in `text`
+    pub timestamps: Vec<u64>,
+}
+
+/// time in ms between two moments of typing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Timing {
+    Fixed(u64),
+    /// a random time in `min..=max`
+    Random { min: u64, max: u64 },
+}
+
+impl Timing {
+    fn sample(&self, rng: &mut Rng) -> u64 {
+        match *self {
+            Timing::Fixed(ms) => ms,
+            Timing::Random { min, max } => rng.range(min, max),
+        }
+    }
+}
+
+/// how `KeyboardAction::from_text` types
+#[derive(Copy, Clone, Debug)]
+pub struct TypingProfile {
+    /// time a key is held (press => release)
+    pub dwell: Timing,
+    /// time between a release and the next press
+    pub flight: Timing,
+    /// seed of the random timings, the same seed gives the same action
+    pub seed: u64,
+    /// layout used to find the key (and `Shift`) of each character
+    pub layout: KeyboardLayout,
+}
+
+impl TypingProfile {
+    pub fn fixed(dwell: u64, flight: u64) -> TypingProfile {
+        TypingProfile {
+            dwell: Timing::Fixed(dwell),
+            flight: Timing::Fixed(flight),
+            seed: 0,
+            layout: KeyboardLayout::Qwerty,
+        }
+    }
+
+    /// `dwell` and `flight` are `(min, max)`
+    pub fn random(dwell: (u64, u64), flight: (u64, u64), seed: u64) -> TypingProfile {
+        TypingProfile {
+            dwell: Timing::Random { min: dwell.0, max: dwell.1 },
+            flight: Timing::Random { min: flight.0, max: flight.1 },
+            seed,
+            layout: KeyboardLayout::Qwerty,
+        }
+    }
+}
+
+/// `dwell` of `TypingProfile::default()`
+pub(crate) const DEFAULT_DWELL: u64 = 80;
+/// `flight` of `TypingProfile::default()`
+pub(crate) const DEFAULT_FLIGHT: u64 = 120;
+
+impl Default for TypingProfile {
+    fn default() -> Self {
+        TypingProfile::fixed(DEFAULT_DWELL, DEFAULT_FLIGHT)
+    }
+}
+
+/// the character typed by keys that are not in the layout tables
+fn fixed_char(key: Keycode) -> Option<char> {
+    match key {
+        Keycode::Space => Some(' '),
+        Keycode::Enter => Some('\n'),
+        Keycode::Tab => Some('\t'),
+        Keycode::Numpad0 => Some('0'),
+        Keycode::Numpad1 => Some('1'),
+        Keycode::Numpad2 => Some('2'),
+        Keycode::Numpad3 => Some('3'),
+        Keycode::Numpad4 => Some('4'),
+        Keycode::Numpad5 => Some('5'),
+        Keycode::Numpad6 => Some('6'),
+        Keycode::Numpad7 => Some('7'),
+        Keycode::Numpad8 => Some('8'),
+        Keycode::Numpad9 => Some('9'),
+        Keycode::NumpadAdd => Some('+'),
+        Keycode::NumpadSubtract => Some('-'),
+        Keycode::NumpadMultiply => Some('*'),
+        Keycode::NumpadDivide => Some('/'),
+        _ => None
+    }
+}
+
+/// the key (and if `Shift` is needed) to type `c`
+fn char_to_key(c: char, layout: KeyboardLayout) -> Option<(Keycode, bool)> {
+    match c {
+        ' ' => Some((Keycode::Space, false)),
+        '\n' => Some((Keycode::Enter, false)),
+        '\t' => Some((Keycode::Tab, false)),
+        _ => layout.char_to_key(c)
+    }
+}
+
+/// the character `from_text` types with `key` alone, without `Shift`
+pub(crate) fn typed_char(key: Keycode) -> Option<char> {
+    let c = fixed_char(key).or_else(|| KeyboardLayout::Qwerty.key_to_char(key, false))?;
+    match char_to_key(c, KeyboardLayout::Qwerty) == Some((key, false)) {
+        true => Some(c),
+        false => None
+    }
+}
+
+impl KeyboardAction {
+    /// Type `text` with the timings of `profile`.
+    /// `Shift` is pressed for the characters that need it and held over consecutive ones.
+    /// fails if a character can not be typed in the layout of `profile`.
+    pub fn from_text(text: &str, profile: TypingProfile) -> Result<KeyboardAction, Error> {
+        let mut rng = Rng::new(profile.seed);
+        let mut action = KeyboardAction {
+            evs: vec![],
+            till: 0,
+        };
+
+        let mut shift_held = false;
+        let mut timestamp = 0;
+        for (index, c) in text.chars().enumerate() {
+            let (code, shift) = char_to_key(c, profile.layout).ok_or_else(|| Error::UnknownKey(c.to_string()))?;
+
+            if index > 0 {
+                timestamp += profile.flight.sample(&mut rng);
+            }
+            let dwell = profile.dwell.sample(&mut rng);
+
+            if shift != shift_held {
+                action.evs.push(KeyboardEv { code: Keycode::LShift, press: shift, timestamp });
+                shift_held = shift;
+                timestamp += dwell / 2;
+            }
+
+            action.evs.push(KeyboardEv { code, press: true, timestamp });
+            timestamp += dwell;
+            action.evs.push(KeyboardEv { code, press: false, timestamp });
+        }
+
+        if shift_held {
+            action.evs.push(KeyboardEv { code: Keycode::LShift, press: false, timestamp });
+        }
+        action.till = timestamp;
+
+        Ok(action)
+    }
+
+    /// What did the user type? See `to_text_with_layout`, the record is read as US QWERTY.
+    pub fn to_text(&self) -> TypedText {
+        self.to_text_with_layout(KeyboardLayout::Qwerty)
+    }
+
+    /// Walk the press/release stream and rebuild the typed text.
+    /// `Shift` and `CapsLock` are tracked, `Backspace` removes the last character,
+    /// and keys pressed while `Control`, `Alt` or `Meta` is held are taken as shortcuts and ignored.
+    pub fn to_text_with_layout(&self, layout: KeyboardLayout) -> TypedText {
+        let mut typed = TypedText {
+            text: String::new(),
+            timestamps: vec![],
+        };
+
+        let mut shift = (false, false);
+        let mut caps_lock = false;
+        // the held modifiers, a held key repeats its presses
+        let mut modifiers: HashSet<Keycode> = HashSet::new();
+
+        for ev in self.evs.iter() {
+            match (ev.code, ev.press) {
+                (Keycode::LShift, press) => shift.0 = press,
+                (Keycode::RShift, press) => shift.1 = press,
+                (code @ (Keycode::LControl | Keycode::RControl | Keycode::LAlt | Keycode::RAlt | Keycode::Meta), true) => {
+                    modifiers.insert(code);
+                }
+                (code @ (Keycode::LControl | Keycode::RControl | Keycode::LAlt | Keycode::RAlt | Keycode::Meta), false) => {
+                    modifiers.remove(&code);
+                }
+                (Keycode::CapsLock, true) => caps_lock = !caps_lock,
+                (_, false) => (),
+                (_, true) if !modifiers.is_empty() => (),
+                (Keycode::Backspace, true) => {
+                    if typed.text.pop().is_some() {
+                        typed.timestamps.pop();
+                    }
+                }
+                (code, true) => {
+                    let shifted = shift.0 || shift.1;
+                    let c = match layout.key_to_char(code, false) {
+                        // `CapsLock` only works on letters
+                        Some(c) if c.is_alphabetic() => layout.key_to_char(code, shifted != caps_lock),
+                        Some(_) => layout.key_to_char(code, shifted),
+                        None => fixed_char(code)
+                    };
+                    if let Some(c) = c {
+                        typed.text.push(c);
+                        typed.timestamps.push(ev.timestamp);
+                    }
+                }
+            }
+        }
+
+        typed
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tap(code: Keycode, timestamp: u64) -> [KeyboardEv; 2] {
+        [
+            KeyboardEv { code, press: true, timestamp },
+            KeyboardEv { code, press: false, timestamp: timestamp + 50 },
+        ]
+    }
+
+    /// the record in `examples/keyboard_display.rs`
+    #[test]
+    fn to_text_hello_world() {
+        let action = KeyboardAction::from_string("{\"evs\":[{\"code\":\"KeyH\",\"press\":true,\"timestamp\":2065},{\"code\":\"KeyH\",\"press\":false,\"timestamp\":2144},{\"code\":\"KeyE\",\"press\":true,\"timestamp\":2391},{\"code\":\"KeyE\",\"press\":false,\"timestamp\":2484},{\"code\":\"KeyL\",\"press\":true,\"timestamp\":2620},{\"code\":\"KeyL\",\"press\":false,\"timestamp\":2700},{\"code\":\"KeyL\",\"press\":true,\"timestamp\":2753},{\"code\":\"KeyL\",\"press\":false,\"timestamp\":2846},{\"code\":\"KeyO\",\"press\":true,\"timestamp\":3109},{\"code\":\"KeyO\",\"press\":false,\"timestamp\":3194},{\"code\":\"Space\",\"press\":true,\"timestamp\":3501},{\"code\":\"Space\",\"press\":false,\"timestamp\":3612},{\"code\":\"KeyW\",\"press\":true,\"timestamp\":3797},{\"code\":\"KeyW\",\"press\":false,\"timestamp\":3878},{\"code\":\"KeyO\",\"press\":true,\"timestamp\":3997},{\"code\":\"KeyO\",\"press\":false,\"timestamp\":4075},{\"code\":\"KeyR\",\"press\":true,\"timestamp\":4123},{\"code\":\"KeyR\",\"press\":false,\"timestamp\":4223},{\"code\":\"KeyL\",\"press\":true,\"timestamp\":4483},{\"code\":\"KeyL\",\"press\":false,\"timestamp\":4569},{\"code\":\"KeyD\",\"press\":true,\"timestamp\":4684},{\"code\":\"KeyD\",\"press\":false,\"timestamp\":4787},{\"code\":\"ShiftRight\",\"press\":true,\"timestamp\":5004},{\"code\":\"Digit1\",\"press\":true,\"timestamp\":5146},{\"code\":\"Digit1\",\"press\":false,\"timestamp\":5235},{\"code\":\"ShiftRight\",\"press\":false,\"timestamp\":5313}],\"till\":6668}").unwrap();
+
+        let typed = action.to_text();
+        assert_eq!(typed.text, "hello world!");
+        assert_eq!(typed.timestamps.len(), 12);
+        assert_eq!(typed.timestamps[0], 2065);
+        assert_eq!(typed.timestamps[11], 5146);
+    }
+
+    #[test]
+    fn to_text_caps_lock_and_backspace() {
+        let mut evs = vec![];
+        evs.extend(tap(Keycode::CapsLock, 0));
+        evs.extend(tap(Keycode::A, 100));
+        evs.extend(tap(Keycode::Key1, 200));
+        evs.extend(tap(Keycode::CapsLock, 300));
+        evs.extend(tap(Keycode::B, 400));
+        evs.extend(tap(Keycode::C, 500));
+        evs.extend(tap(Keycode::Backspace, 600));
+        // ctrl + v is not typed
+        evs.push(KeyboardEv { code: Keycode::LControl, press: true, timestamp: 700 });
+        evs.extend(tap(Keycode::V, 710));
+        evs.push(KeyboardEv { code: Keycode::LControl, press: false, timestamp: 800 });
+
+        let action = KeyboardAction { evs, till: 900 };
+        let typed = action.to_text();
+        assert_eq!(typed.text, "A1b");
+        assert_eq!(typed.timestamps, vec![100, 200, 400]);
+    }
+
+    /// a held ctrl repeats its press but is released once
+    #[test]
+    fn to_text_repeated_modifier() {
+        let mut evs = vec![];
+        evs.push(KeyboardEv { code: Keycode::LControl, press: true, timestamp: 0 });
+        evs.push(KeyboardEv { code: Keycode::LControl, press: true, timestamp: 30 });
+        evs.push(KeyboardEv { code: Keycode::LControl, press: true, timestamp: 60 });
+        evs.extend(tap(Keycode::C, 70));
+        evs.push(KeyboardEv { code: Keycode::LControl, press: false, timestamp: 200 });
+        evs.extend(tap(Keycode::D, 300));
+
+        let action = KeyboardAction { evs, till: 400 };
+        assert_eq!(action.to_text().text, "d");
+    }
+
+    #[test]
+    fn to_text_azerty() {
+        let mut evs = vec![];
+        evs.extend(tap(Keycode::Q, 0));
+        evs.extend(tap(Keycode::Key1, 100));
+
+        let action = KeyboardAction { evs, till: 200 };
+        assert_eq!(action.to_text_with_layout(KeyboardLayout::Azerty).text, "a&");
+    }
+
+    #[test]
+    fn from_text_fixed() {
+        let action = KeyboardAction::from_text("Hi!", TypingProfile::fixed(80, 120)).unwrap();
+        let evs: Vec<(Keycode, bool, u64)> = action.evs.iter().map(|ev| (ev.code, ev.press, ev.timestamp)).collect();
+
+        assert_eq!(evs, vec![
+            (Keycode::LShift, true, 0),
+            (Keycode::H, true, 40),
+            (Keycode::H, false, 120),
+            (Keycode::LShift, false, 240),
+            (Keycode::I, true, 280),
+            (Keycode::I, false, 360),
+            (Keycode::LShift, true, 480),
+            (Keycode::Key1, true, 520),
+            (Keycode::Key1, false, 600),
+            (Keycode::LShift, false, 600),
+        ]);
+        assert_eq!(action.till, 600);
+        assert_eq!(action.to_text().text, "Hi!");
+    }
+
+    #[test]
+    fn from_text_random() {
+        let profile = TypingProfile::random((50, 90), (100, 200), 42);
+        let action = KeyboardAction::from_text("Hello world!\n", profile).unwrap();
+        assert_eq!(action.to_text().text, "Hello world!\n");
+
+        for pair in action.evs.windows(2) {
+            assert!(pair[0].timestamp <= pair[1].timestamp);
+        }
+
+        // the same seed gives the same action
+        let again = KeyboardAction::from_text("Hello world!\n", profile).unwrap();
+        assert_eq!(action.to_string().unwrap(), again.to_string().unwrap());
+    }
+
+    #[test]
+    fn from_text_unknown_char() {
+        assert!(KeyboardAction::from_text("ß", TypingProfile::default()).is_err());
+    }
+}
+// endregion
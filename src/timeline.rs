@@ -0,0 +1,65 @@
+use std::hash::Hash;
+use device_query::Keycode;
+use crate::{KeyboardEv, MouseEv, MouseMapper};
+
+// region timed event
+/// Events on a timeline, for the edits shared by keyboard and mouse actions.
+pub(crate) trait TimedEvent: Copy {
+    /// the key or the button
+    type Key: Eq + Hash + Copy;
+
+    fn timestamp(&self) -> u64;
+
+    fn set_timestamp(&mut self, timestamp: u64);
+
+    /// the key or the button, and if it is pressed
+    fn key(&self) -> (Self::Key, bool);
+
+    /// the release of the key or the button of this event, at the same time
+    fn release(&self) -> Self;
+}
+
+impl TimedEvent for KeyboardEv {
+    type Key = Keycode;
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+    }
+
+    fn key(&self) -> (Keycode, bool) {
+        (self.code, self.press)
+    }
+
+    fn release(&self) -> KeyboardEv {
+        KeyboardEv { press: false, ..*self }
+    }
+}
+
+impl TimedEvent for MouseEv {
+    type Key = usize;
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn set_timestamp(&mut self, timestamp: u64) {
+        self.timestamp = timestamp;
+    }
+
+    fn key(&self) -> (usize, bool) {
+        MouseMapper::ev_name_to_dq(self.ev_name)
+    }
+
+    fn release(&self) -> MouseEv {
+        let (button, _) = MouseMapper::ev_name_to_dq(self.ev_name);
+        MouseEv {
+            ev_name: MouseMapper::dq_to_ev_name(button, false).unwrap_or(self.ev_name),
+            ..*self
+        }
+    }
+}
+// endregion
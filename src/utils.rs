@@ -6,4 +6,54 @@ pub fn set_timeout<T>(mut callback: T, ms: u64)
     where T: FnMut() -> () {
     sleep(Duration::from_millis(ms));
     callback();
-}
\ No newline at end of file
+}
+
+/// a tiny seeded pseudo-random generator (xorshift64*), good enough for timing jitter
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift gets stuck at zero
+        Rng { state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// a number in `min..=max`
+    pub fn range(&mut self, min: u64, max: u64) -> u64 {
+        if max <= min {
+            return min;
+        }
+        match (max - min).checked_add(1) {
+            Some(span) => min + self.next_u64() % span,
+            // the full range of u64
+            None => self.next_u64()
+        }
+    }
+}
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rng_range() {
+        let mut rng = Rng::new(42);
+        for _ in 0..100 {
+            assert!((50..=90).contains(&rng.range(50, 90)));
+        }
+        assert_eq!(rng.range(7, 7), 7);
+        // no overflow on the full range
+        rng.range(0, u64::MAX);
+    }
+}
+// endregion
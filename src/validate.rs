@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use device_query::Keycode;
+use crate::{KeyboardAction, MouseAction, MouseMapper};
+
+// region diagnostic
+/// how bad an issue is, players refuse the actions with `Error`s in strict mode
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// harmless, e.g. an auto-repeat press
+    Info,
+    /// played, but likely not what was meant
+    Warning,
+    /// played wrong, or leaves the OS in a bad state (e.g. a key held down)
+    Error,
+}
+
+/// problems found by `validate`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// the timestamp is earlier than the one of the event before
+    OutOfOrder { previous: u64, timestamp: u64 },
+    /// the key or button is pressed and never released
+    MissingRelease,
+    /// the key or button is released without being pressed
+    OrphanRelease,
+    /// the key or button is pressed again while held (e.g. auto-repeat)
+    RepeatedPress,
+    /// the event is after `till` of the action
+    AfterTill { timestamp: u64, till: u64 },
+    /// the position is out of `[0, width) x [0, height)`
+    OffScreen { position: (i32, i32), screen: [u32; 2] },
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Issue::OutOfOrder { .. } => Severity::Error,
+            Issue::MissingRelease => Severity::Error,
+            Issue::OrphanRelease => Severity::Warning,
+            Issue::RepeatedPress => Severity::Info,
+            Issue::AfterTill { .. } => Severity::Warning,
+            Issue::OffScreen { .. } => Severity::Error,
+        }
+    }
+}
+
+/// an issue of an action
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// index (from 0) of the event
+    pub index: usize,
+    pub issue: Issue,
+}
+
+impl Diagnostic {
+    fn new(index: usize, issue: Issue) -> Diagnostic {
+        Diagnostic {
+            severity: issue.severity(),
+            index,
+            issue,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: event #{}: ", severity, self.index)?;
+        match &self.issue {
+            Issue::OutOfOrder { previous, timestamp } => {
+                write!(f, "timestamp {} is earlier than {} of the event before", timestamp, previous)
+            }
+            Issue::MissingRelease => write!(f, "pressed and never released"),
+            Issue::OrphanRelease => write!(f, "released without being pressed"),
+            Issue::RepeatedPress => write!(f, "pressed again while held"),
+            Issue::AfterTill { timestamp, till } => write!(f, "timestamp {} is after the end {}", timestamp, till),
+            Issue::OffScreen { position, screen } => {
+                write!(f, "position ({}, {}) is out of the screen {}x{}", position.0, position.1, screen[0], screen[1])
+            }
+        }
+    }
+}
+
+/// if any of the diagnostics is an error
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+/// check the order, the press/release pairs and `till` of `(key, press, timestamp)`
+fn validate_events<K, I>(events: I, till: u64) -> Vec<Diagnostic>
+    where K: Eq + Hash, I: Iterator<Item=(K, bool, u64)> {
+    let mut diagnostics = vec![];
+    // index of the press of each held key
+    let mut held: HashMap<K, usize> = HashMap::new();
+    let mut previous = 0;
+
+    for (index, (key, press, timestamp)) in events.enumerate() {
+        if timestamp < previous {
+            diagnostics.push(Diagnostic::new(index, Issue::OutOfOrder { previous, timestamp }));
+        }
+        previous = previous.max(timestamp);
+        if timestamp > till {
+            diagnostics.push(Diagnostic::new(index, Issue::AfterTill { timestamp, till }));
+        }
+
+        match (press, held.contains_key(&key)) {
+            (true, true) => diagnostics.push(Diagnostic::new(index, Issue::RepeatedPress)),
+            (true, false) => {
+                held.insert(key, index);
+            }
+            (false, true) => {
+                held.remove(&key);
+            }
+            (false, false) => diagnostics.push(Diagnostic::new(index, Issue::OrphanRelease)),
+        }
+    }
+
+    for index in held.into_values() {
+        diagnostics.push(Diagnostic::new(index, Issue::MissingRelease));
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.index);
+    diagnostics
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    /// Find the problems of the action: timestamps out of order, presses with no release, releases with no press,
+    /// repeated presses and events after `till`. Diagnostics are sorted by the index of the event.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate_events::<Keycode, _>(self.evs.iter().map(|ev| (ev.code, ev.press, ev.timestamp)), self.till)
+    }
+}
+
+impl MouseAction {
+    /// `KeyboardAction.validate` for the buttons.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate_events(self.evs.iter().map(|ev| {
+            let (button, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+            (button, press, ev.timestamp)
+        }), self.till)
+    }
+
+    /// `validate` and the positions out of the screen of `[width, height]` (see `Metadata.screen_size`).
+    pub fn validate_on_screen(&self, screen: [u32; 2]) -> Vec<Diagnostic> {
+        let mut diagnostics = self.validate();
+        for (index, ev) in self.evs.iter().enumerate() {
+            let (x, y) = ev.position;
+            if x < 0 || y < 0 || x as u32 >= screen[0] || y as u32 >= screen[1] {
+                diagnostics.push(Diagnostic::new(index, Issue::OffScreen { position: ev.position, screen }));
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.index);
+        diagnostics
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{KeyboardEv, MouseEv, MouseEventName};
+
+    #[test]
+    fn validate_keyboard() {
+        let action = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::LShift, press: false, timestamp: 0 },
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 150 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 120 },
+                KeyboardEv { code: Keycode::B, press: true, timestamp: 300 },
+            ],
+            till: 200,
+        };
+        let issues: Vec<(usize, Issue)> = action.validate().into_iter().map(|d| (d.index, d.issue)).collect();
+        assert_eq!(issues, vec![
+            (0, Issue::OrphanRelease),
+            (2, Issue::RepeatedPress),
+            (3, Issue::OutOfOrder { previous: 150, timestamp: 120 }),
+            (4, Issue::AfterTill { timestamp: 300, till: 200 }),
+            (4, Issue::MissingRelease),
+        ]);
+        assert!(has_errors(&action.validate()));
+        assert_eq!(action.validate()[4].to_string(), "error: event #4: pressed and never released");
+
+        let fine = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 150 },
+            ],
+            till: 200,
+        };
+        assert!(fine.validate().is_empty());
+    }
+
+    #[test]
+    fn validate_mouse() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::LeftDown, position: (100, 100), timestamp: 0 },
+                MouseEv { ev_name: MouseEventName::RightUp, position: (100, 100), timestamp: 10 },
+                MouseEv { ev_name: MouseEventName::LeftUp, position: (1920, -1), timestamp: 20 },
+            ],
+            till: 20,
+        };
+        let issues: Vec<(usize, Issue)> = action.validate_on_screen([1920, 1080]).into_iter().map(|d| (d.index, d.issue)).collect();
+        assert_eq!(issues, vec![
+            (1, Issue::OrphanRelease),
+            (2, Issue::OffScreen { position: (1920, -1), screen: [1920, 1080] }),
+        ]);
+        assert!(!has_errors(&action.validate()));
+    }
+}
+// endregion
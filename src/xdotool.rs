@@ -0,0 +1,255 @@
+use crate::{Error, KeyboardAction, KeyboardEv, KeyboardMapper, MouseAction, MouseEv, MouseMapper};
+
+// region export
+/// Render the actions as a shell script of `xdotool` commands (`keydown`, `keyup`, `mousemove`, `mousedown`,
+/// `mouseup`) with `sleep` between them. Keys without keysym are left out.
+pub(crate) fn render_xdotool(keyboard: Option<&KeyboardAction>, mouse: Option<&MouseAction>) -> String {
+    let mut commands: Vec<(u64, String)> = vec![];
+    let mut till = 0;
+
+    if let Some(keyboard) = keyboard {
+        for ev in keyboard.evs.iter() {
+            if let Some(keysym) = KeyboardMapper::dq_to_keysym(ev.code) {
+                let command = if ev.press { "keydown" } else { "keyup" };
+                commands.push((ev.timestamp, format!("{} {}", command, keysym)));
+            }
+        }
+        till = till.max(keyboard.till);
+    }
+    if let Some(mouse) = mouse {
+        for ev in mouse.evs.iter() {
+            let (button, press) = MouseMapper::ev_name_to_dq(ev.ev_name);
+            let command = if press { "mousedown" } else { "mouseup" };
+            commands.push((ev.timestamp, format!(
+                "mousemove {} {} {} {}",
+                ev.position.0, ev.position.1, command, MouseMapper::dq_to_x(button).unwrap_or(1)
+            )));
+        }
+        till = till.max(mouse.till);
+    }
+    // stable, the keyboard goes first at the same time
+    commands.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut script = String::from("#!/bin/sh\n# generated by toca\n");
+    let mut now = 0;
+    for (timestamp, command) in commands {
+        if timestamp > now {
+            script.push_str(&format!("sleep {}\n", seconds(timestamp - now)));
+            now = timestamp;
+        }
+        script.push_str(&format!("xdotool {}\n", command));
+    }
+    if till > now {
+        script.push_str(&format!("sleep {}\n", seconds(till - now)));
+    }
+    script
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+// endregion
+
+// region import
+/// the aliases of keysyms `xdotool` takes besides the names in X11
+fn keysym_alias(keysym: &str) -> &str {
+    match keysym {
+        "ctrl" => "Control_L",
+        "shift" => "Shift_L",
+        "alt" => "Alt_L",
+        "super" | "meta" => "Super_L",
+        "Enter" | "enter" => "Return",
+        _ => keysym
+    }
+}
+
+/// Parse a script of `xdotool` commands, the subset `render_xdotool` writes:
+/// `xdotool` with (chained) `keydown`, `keyup`, `mousemove`, `mousedown`, `mouseup` and `sleep`,
+/// and the `sleep` of shell. Empty lines and comments are skipped.
+pub fn parse_xdotool(script: &str) -> Result<(KeyboardAction, MouseAction), Error> {
+    let mut keyboard = KeyboardAction { evs: vec![], till: 0 };
+    let mut mouse = MouseAction { evs: vec![], till: 0 };
+    let mut now: u64 = 0;
+    let mut position = (0, 0);
+
+    for (index, line) in script.lines().enumerate() {
+        let line_number = index + 1;
+        // words with their columns (from 1)
+        let mut words = vec![];
+        let mut start = None;
+        for (column, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(column),
+                (true, Some(from)) => {
+                    words.push((from + 1, &line[from..column]));
+                    start = None;
+                }
+                _ => ()
+            }
+        }
+        if words.is_empty() || words[0].1.starts_with('#') {
+            continue;
+        }
+
+        let error = |column: usize, message: String| Error::Parse { line: line_number, column, message };
+        let mut words = words.into_iter();
+        let (column, program) = words.next().unwrap();
+        // the argument after the word at `column`
+        let mut argument = |what: &str, column: usize| words.next().ok_or_else(|| error(column, format!("expect {}", what)));
+
+        match program {
+            "sleep" => {
+                let (column, secs) = argument("seconds", column)?;
+                now += parse_seconds(secs).ok_or_else(|| error(column, format!("invalid seconds `{}`", secs)))?;
+            }
+            "xdotool" => {
+                while let Ok((column, command)) = argument("a command", column) {
+                    match command {
+                        "keydown" | "keyup" => {
+                            let (column, keys) = argument("a key", column)?;
+                            for keysym in keys.split('+') {
+                                let code = KeyboardMapper::keysym_to_dq(keysym_alias(keysym))
+                                    .ok_or_else(|| error(column, format!("unknown keysym `{}`", keysym)))?;
+                                keyboard.evs.push(KeyboardEv { code, press: command == "keydown", timestamp: now });
+                            }
+                        }
+                        "mousemove" => {
+                            let (column_x, x) = argument("x", column)?;
+                            let (column_y, y) = argument("y", column_x)?;
+                            position = (
+                                x.parse().map_err(|_| error(column_x, format!("invalid x `{}`", x)))?,
+                                y.parse().map_err(|_| error(column_y, format!("invalid y `{}`", y)))?,
+                            );
+                        }
+                        "mousedown" | "mouseup" => {
+                            let (column, button) = argument("a button", column)?;
+                            let ev_name = button.parse().ok()
+                                .and_then(MouseMapper::x_to_dq)
+                                .and_then(|button| MouseMapper::dq_to_ev_name(button, command == "mousedown"))
+                                .ok_or_else(|| error(column, format!("unknown button `{}`", button)))?;
+                            mouse.evs.push(MouseEv { ev_name, position, timestamp: now });
+                        }
+                        "sleep" => {
+                            let (column, secs) = argument("seconds", column)?;
+                            now += parse_seconds(secs).ok_or_else(|| error(column, format!("invalid seconds `{}`", secs)))?;
+                        }
+                        command => return Err(error(column, format!("unsupported command `{}`", command)))
+                    }
+                }
+            }
+            program => return Err(error(column, format!("unsupported command `{}`", program)))
+        }
+    }
+
+    keyboard.till = now;
+    mouse.till = now;
+    Ok((keyboard, mouse))
+}
+
+/// seconds => milliseconds
+fn parse_seconds(secs: &str) -> Option<u64> {
+    match secs.parse::<f64>() {
+        Ok(secs) if secs >= 0.0 && secs.is_finite() => Some((secs * 1000.0).round() as u64),
+        _ => None
+    }
+}
+// endregion
+
+// region action
+impl KeyboardAction {
+    /// a shell script of `xdotool` to play the action without toca (on linux)
+    pub fn to_xdotool(&self) -> String {
+        render_xdotool(Some(self), None)
+    }
+
+    /// parse a script of `xdotool` (see `parse_xdotool`), the mouse commands are left out.
+    pub fn from_xdotool(script: &str) -> Result<KeyboardAction, Error> {
+        Ok(parse_xdotool(script)?.0)
+    }
+}
+
+impl MouseAction {
+    /// a shell script of `xdotool` to play the action without toca (on linux)
+    pub fn to_xdotool(&self) -> String {
+        render_xdotool(None, Some(self))
+    }
+
+    /// parse a script of `xdotool` (see `parse_xdotool`), the keyboard commands are left out.
+    pub fn from_xdotool(script: &str) -> Result<MouseAction, Error> {
+        Ok(parse_xdotool(script)?.1)
+    }
+}
+// endregion
+
+// region unit test
+#[cfg(test)]
+mod test {
+    use super::*;
+    use device_query::Keycode;
+    use crate::MouseEventName;
+
+    #[test]
+    fn keyboard_xdotool() {
+        let action = KeyboardAction {
+            evs: vec![
+                KeyboardEv { code: Keycode::LShift, press: true, timestamp: 100 },
+                KeyboardEv { code: Keycode::A, press: true, timestamp: 150 },
+                KeyboardEv { code: Keycode::A, press: false, timestamp: 150 },
+                KeyboardEv { code: Keycode::LShift, press: false, timestamp: 1200 },
+            ],
+            till: 1500,
+        };
+        let script = action.to_xdotool();
+        assert_eq!(
+            script,
+            "#!/bin/sh\n# generated by toca\n\
+            sleep 0.100\nxdotool keydown Shift_L\n\
+            sleep 0.050\nxdotool keydown a\nxdotool keyup a\n\
+            sleep 1.050\nxdotool keyup Shift_L\n\
+            sleep 0.300\n"
+        );
+
+        let again = KeyboardAction::from_xdotool(&script).unwrap();
+        assert_eq!(again.evs.len(), 4);
+        assert_eq!(again.evs[2].code, Keycode::A);
+        assert_eq!(again.evs[2].timestamp, 150);
+        assert_eq!(again.till, 1500);
+    }
+
+    #[test]
+    fn mouse_xdotool() {
+        let action = MouseAction {
+            evs: vec![
+                MouseEv { ev_name: MouseEventName::RightDown, position: (10, 20), timestamp: 0 },
+                MouseEv { ev_name: MouseEventName::RightUp, position: (30, 40), timestamp: 80 },
+            ],
+            till: 80,
+        };
+        let script = action.to_xdotool();
+        assert!(script.contains("xdotool mousemove 10 20 mousedown 3\n"));
+
+        let again = MouseAction::from_xdotool(&script).unwrap();
+        assert!(matches!(again.evs[1].ev_name, MouseEventName::RightUp));
+        assert_eq!(again.evs[1].position, (30, 40));
+        assert_eq!(again.evs[1].timestamp, 80);
+    }
+
+    #[test]
+    fn parse_xdotool_subset() {
+        let (keyboard, mouse) = parse_xdotool("xdotool keydown ctrl+c sleep 0.5 keyup ctrl+c\n\nxdotool mousemove 1 2 mousedown 1").unwrap();
+        assert_eq!(keyboard.evs.len(), 4);
+        assert_eq!(keyboard.evs[0].code, Keycode::LControl);
+        assert_eq!(keyboard.evs[3].timestamp, 500);
+        assert_eq!(mouse.evs[0].position, (1, 2));
+
+        match parse_xdotool("sleep 1\nxdotool keydown a type hello") {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((line, column), (2, 19)),
+            _ => panic!("expect a parse error"),
+        }
+        match parse_xdotool("xdotool keydown Hyper_Q") {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((line, column), (1, 17)),
+            _ => panic!("expect a parse error"),
+        }
+    }
+}
+// endregion